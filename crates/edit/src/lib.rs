@@ -1,4 +1,4 @@
-use elysh_syntax::{Args, Chars, Command, CommandError};
+use elysh_syntax::{Args, Chars, Command, CommandError, List, Pipeline};
 use std::{fmt, ops};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -93,7 +93,7 @@ impl Edit {
                 let mut end_chars = end.chars();
 
                 if let (Some(start), Some(end)) = (start_args.last(), end_chars.next()) {
-                    if let Some(quote) = start.quote() {
+                    if let Some(quote) = start.value().and_then(|value| value.open_quote()) {
                         if quote.as_char() == character && quote.as_char() == end {
                             self.next(1);
 
@@ -111,7 +111,7 @@ impl Edit {
 
                 let start_ends_with_space = start_args
                     .last()
-                    .map(|arg| arg.is_whitespace())
+                    .map(|arg| arg.is_whitespace() && !arg.is_comment())
                     .unwrap_or(false);
 
                 let end_starts_with_space = end_chars
@@ -220,10 +220,38 @@ impl Edit {
         }
     }
 
-    /// Try parsing as a command.
+    /// Try parsing as a command, up to any comment.
     #[inline]
     pub fn command(&self) -> Result<Command<'_>, CommandError> {
-        Command::try_parse(&self.buffer)
+        Command::try_parse(self.split_comment().0)
+    }
+
+    /// Try parsing as a pipeline, up to any comment.
+    #[inline]
+    pub fn pipeline(&self) -> Result<Pipeline<'_>, CommandError<'_>> {
+        Pipeline::try_parse(self.split_comment().0)
+    }
+
+    /// Try parsing as a command list, up to any comment.
+    #[inline]
+    pub fn list(&self) -> Result<List<'_>, CommandError<'_>> {
+        List::try_parse(self.split_comment().0)
+    }
+
+    /// Split before the first comment, the second part being empty when there is none.
+    #[inline]
+    pub fn split_comment(&self) -> (&str, &str) {
+        let mut args = Args::new(&self.buffer);
+
+        loop {
+            let start = args.offset();
+
+            match args.next() {
+                Some(arg) if arg.is_comment() => return self.buffer.split_at(start),
+                Some(_arg) => {}
+                None => return (&self.buffer, ""),
+            }
+        }
     }
 
     /// Return start character iterator.
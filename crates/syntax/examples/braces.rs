@@ -0,0 +1,12 @@
+use elysh_syntax::Args;
+
+fn main() {
+    let command_line = "cp file.{rs,bak} dir{1..5} {01..10..3} {a..z..5} {z..w} a{b,c{d,e}}f {x}{1,2} ${HOME}/{bin,lib} '{a,b}'";
+    println!("{:?}", command_line);
+
+    for arg in Args::new(command_line) {
+        if let Some(value) = arg.value() {
+            println!("{:?} -> {:?}", value.as_str(), value.braces());
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use elysh_syntax::Args;
+
+fn main() {
+    let command_line =
+        r#"touch foo\ bar "say \"hi\" \$HOME \n" 'C:\no\escapes' $'tab\there\x41\101é' \*.o a\;b"#;
+    println!("{:?}", command_line);
+
+    for arg in Args::new(command_line) {
+        println!("{:?}", arg);
+
+        if let Some(value) = arg.value() {
+            println!("{:?}", value.unescape());
+        }
+    }
+
+    let command_line = "echo one \\\n two";
+    println!("{:?}", command_line);
+
+    for arg in Args::new(command_line) {
+        println!("{:?}", arg);
+    }
+}
@@ -0,0 +1,28 @@
+use elysh_syntax::List;
+
+fn main() {
+    let list = "cat <<EOF\nhello $USER\nEOF";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list);
+
+    let list = "cat <<'EOF' | tr a-z A-Z\n$HOME is not expanded\nEOF";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list);
+
+    let list = "cat <<-EOF\n\tindented\n\tEOF";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list);
+
+    let list = "cat <<EOF\nstill typing";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list.map(|list| list.needs_heredoc()));
+
+    let list = "cat <<< \"a here-string\"";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list);
+}
@@ -0,0 +1,23 @@
+use elysh_syntax::List;
+
+fn main() {
+    let list = "cd src && cargo build || echo \"build failed\"";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list);
+
+    let list = "make;make install | tee log";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list);
+
+    let list = "cargo build & vim src/main.rs";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list);
+
+    let list = "ls; ";
+    let list = List::try_parse(list);
+
+    println!("{:?}", list);
+}
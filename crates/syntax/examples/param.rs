@@ -0,0 +1,30 @@
+use elysh_syntax::{Args, Parts};
+
+fn main() {
+    let command_line = "echo $HOME/bin ${PATH} ${#PATH}";
+    println!("{:?}", command_line);
+
+    for arg in Args::new(command_line) {
+        println!("{:?}", arg);
+    }
+
+    let command_line = "echo ${EDITOR:-vim} ${name:=elysh} ${file%.rs}.bak ${path//\\//:}";
+    println!("{:?}", command_line);
+
+    for arg in Args::new(command_line) {
+        println!("{:?}", arg);
+    }
+
+    let command_line = "echo \"$USER's ${HOME}\" '$USER'";
+    println!("{:?}", command_line);
+
+    for arg in Args::new(command_line) {
+        if let Some(value) = arg.value() {
+            println!("{:?}", value.parts().collect::<Vec<_>>());
+        }
+    }
+
+    let string = "${message:?not set} costs $5";
+    println!("{:?}", string);
+    println!("{:?}", Parts::new(string).collect::<Vec<_>>());
+}
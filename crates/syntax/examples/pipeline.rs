@@ -0,0 +1,18 @@
+use elysh_syntax::Pipeline;
+
+fn main() {
+    let pipeline = "ls -aFhl | grep foo";
+    let pipeline = Pipeline::try_parse(pipeline);
+
+    println!("{:?}", pipeline);
+
+    let pipeline = "ls|grep \"foo | bar\"|wc -l";
+    let pipeline = Pipeline::try_parse(pipeline);
+
+    println!("{:?}", pipeline);
+
+    let pipeline = "LD_DEBUG=\"1\" ls | ";
+    let pipeline = Pipeline::try_parse(pipeline);
+
+    println!("{:?}", pipeline);
+}
@@ -0,0 +1,24 @@
+use elysh_syntax::Programs;
+
+fn main() {
+    let command_line = "FOO=1 ls -l | grep x && true";
+    println!("{:?}", command_line);
+
+    for program in Programs::new(command_line) {
+        println!("{:?}", program);
+    }
+
+    let command_line = "if test -d src; then ll src; fi > out";
+    println!("{:?}", command_line);
+
+    for program in Programs::new(command_line) {
+        println!("{:?}", program);
+    }
+
+    let command_line = "FOO=\"a b\" l\"s\" x; for x in a b; do g $x\ndone";
+    println!("{:?}", command_line);
+
+    for program in Programs::new(command_line) {
+        println!("{:?}", program);
+    }
+}
@@ -0,0 +1,23 @@
+use elysh_syntax::Command;
+
+fn main() {
+    let command = "make > build.log";
+    let command = Command::try_parse(command);
+
+    println!("{:?}", command);
+
+    let command = "make -j4 >>build.log 2>&1";
+    let command = Command::try_parse(command);
+
+    println!("{:?}", command);
+
+    let command = "cat < \"input file\" &> output";
+    let command = Command::try_parse(command);
+
+    println!("{:?}", command);
+
+    let command = "echo 2 > ";
+    let command = Command::try_parse(command);
+
+    println!("{:?}", command);
+}
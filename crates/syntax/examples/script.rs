@@ -0,0 +1,48 @@
+use elysh_syntax::Script;
+
+fn main() {
+    let script = "if test -d src; then echo dir; elif true; then :; else echo none; fi";
+    let script = Script::try_parse(script);
+
+    println!("{:?}", script);
+
+    let script = "for file in *.rs; do\n    wc -l $file\n    break\ndone";
+    let script = Script::try_parse(script);
+
+    println!("{:?}", script);
+
+    let script = "case $1 in\n    -h | --help) usage;;\n    *.txt) cat $1;;\n    *) exit 1\nesac";
+    let script = Script::try_parse(script);
+
+    println!("{:?}", script);
+
+    let script = "while true; do";
+    let script = Script::try_parse(script);
+
+    println!("{:?}", script);
+
+    let script = "echo done; done";
+    let script = Script::try_parse(script);
+
+    println!("{:?}", script);
+
+    let script = "mkcd() { mkdir -p \"$1\" && cd \"$1\"; }";
+    let script = Script::try_parse(script);
+
+    println!("{:?}", script);
+
+    let script = "#!/usr/bin/env elysh\n# greet\necho hi # there\necho 'a#b' c#d";
+    let script = Script::try_parse(script);
+
+    println!("{:?}", script);
+
+    let source = "echo one\necho two\nfi\n";
+    let script = Script::try_parse(source);
+
+    println!("{:?}", script.map_err(|error| error.line(source)));
+
+    let source = "if true; then\n    echo yes\n";
+    let script = Script::try_parse(source);
+
+    println!("{:?}", script.map_err(|error| error.line(source)));
+}
@@ -0,0 +1,29 @@
+use elysh_syntax::{Args, Part};
+
+fn main() {
+    let command_line = "kill $(pgrep foo) \"$(date +%s; echo \"done\")\" `uname -r`";
+    println!("{:?}", command_line);
+
+    for arg in Args::new(command_line) {
+        println!("{:?}", arg);
+
+        if let Some(value) = arg.value() {
+            for part in value.parts() {
+                if let Part::Command(list) = part {
+                    println!("{:?}", list);
+                }
+            }
+
+            if let Some(list) = value.command() {
+                println!("{:?}", list);
+            }
+        }
+    }
+
+    let command_line = "echo $(echo $(echo nested) | tr a-z A-Z) && ls";
+    println!("{:?}", command_line);
+
+    for arg in Args::new(command_line) {
+        println!("{:?}", arg);
+    }
+}
@@ -1,17 +1,31 @@
-use crate::{Common, Quote, Token, Value};
+use crate::{Common, Heredoc, Operator, Quote, Token, Value};
 
 #[derive(Clone, Debug)]
 pub enum Arg<'a> {
+    Operator(Operator),
     Value(Value<'a>),
     Whitespace(&'a str),
+    Heredoc(Heredoc<'a>),
 }
 
 #[derive(Clone, Debug)]
 pub struct Args<'a> {
     iter: Common<'a>,
+    /// Is the next value the delimiter of a here-document, and does it remove leading tabs?
+    delimiter: Option<bool>,
+    /// The delimiters of here-documents whose bodies begin on the next line, along with whether
+    /// each removes leading tabs, and expands its body.
+    heredocs: Vec<(String, bool, bool)>,
+    /// Is the iterator at the start of the here-document bodies?
+    at_body: bool,
 }
 
 impl<'a> Arg<'a> {
+    #[inline]
+    pub const fn is_operator(&self) -> bool {
+        matches!(self, Arg::Operator(_))
+    }
+
     #[inline]
     pub const fn is_value(&self) -> bool {
         matches!(self, Arg::Value(_))
@@ -23,7 +37,20 @@ impl<'a> Arg<'a> {
     }
 
     #[inline]
-    pub const fn is_incomplete(&self) -> bool {
+    pub const fn is_heredoc(&self) -> bool {
+        matches!(self, Arg::Heredoc(_))
+    }
+
+    /// Is this a comment, from a `#` beginning a word, to the end of the line?
+    ///
+    /// Comments are whitespace, as far as commands are concerned.
+    #[inline]
+    pub fn is_comment(&self) -> bool {
+        matches!(self, Arg::Whitespace(whitespace) if whitespace.starts_with('#'))
+    }
+
+    #[inline]
+    pub fn is_incomplete(&self) -> bool {
         match self {
             Arg::Value(value) => value.is_incomplete(),
             _ => false,
@@ -33,8 +60,18 @@ impl<'a> Arg<'a> {
     #[inline]
     pub const fn as_str(&self) -> &'a str {
         match self {
+            Arg::Operator(operator) => operator.as_str(),
             Arg::Value(value) => value.as_str(),
             Arg::Whitespace(whitespace) => whitespace,
+            Arg::Heredoc(heredoc) => heredoc.body,
+        }
+    }
+
+    #[inline]
+    pub const fn value(&self) -> Option<Value<'a>> {
+        match self {
+            Arg::Value(value) => Some(*value),
+            _ => None,
         }
     }
 
@@ -52,7 +89,12 @@ impl<'a> Args<'a> {
     pub fn new(string: &'a str) -> Self {
         let iter = Common::new(string);
 
-        Self { iter }
+        Self {
+            iter,
+            delimiter: None,
+            heredocs: Vec::new(),
+            at_body: false,
+        }
     }
 
     #[inline]
@@ -66,24 +108,77 @@ impl<'a> Iterator for Args<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<Arg<'a>> {
+        if self.at_body {
+            if !self.heredocs.is_empty() {
+                let (delimiter, strip_tabs, expand) = self.heredocs.remove(0);
+                let heredoc = self.iter.next_heredoc(&delimiter, strip_tabs, expand);
+
+                return Some(Arg::Heredoc(heredoc));
+            }
+
+            self.at_body = false;
+        }
+
         let iter = &mut self.iter;
+        let start = iter.offset();
         let character = iter.next()?;
         let token = match character {
             '`' | '\'' | '"' => {
                 // SAFETY: match arm ensures `quote` is valid.
                 let quote = unsafe { Quote::from_char_unchecked(character) };
+                let token = self.iter.next_string(quote);
+
+                self.iter.next_joined(start, token)
+            }
+            // SAFETY: match arm ensures `character` begins an operator.
+            character if Operator::is_start(character) => unsafe {
+                self.iter.next_operator(character)
+            },
+            // line continuation
+            '\\' if self.iter.peek() == Some('\n') => self.iter.next_whitespace(),
+            // here-document bodies begin on the next line
+            character if character.is_whitespace() && !self.heredocs.is_empty() => {
+                let token = self.iter.next_line_whitespace(character);
+
+                self.at_body = token.as_str().ends_with('\n');
 
-                self.iter.next_string(quote)
+                token
             }
             character if character.is_whitespace() => self.iter.next_whitespace(),
-            _word => self.iter.next_word(),
+            '#' => self.iter.next_comment(),
+            _word => {
+                let token = self.iter.next_word();
+
+                self.iter.next_joined(start, token)
+            }
         };
 
         let arg = match token {
+            Token::Operator(operator) => Arg::Operator(operator),
             Token::Value(value) => Arg::Value(value),
             Token::Whitespace(string) => Arg::Whitespace(string),
         };
 
+        match &arg {
+            Arg::Operator(operator) => {
+                self.delimiter = operator
+                    .is_heredoc()
+                    .then(|| *operator == Operator::DoubleLessDash);
+            }
+            Arg::Value(value) => {
+                if let Some(strip_tabs) = self.delimiter.take() {
+                    // a quoted delimiter, even in part, leaves the body as-is
+                    let expand = !value.is_quoted()
+                        && !value.as_str().contains(|character| {
+                            character == '\\' || Quote::from_char(character).is_some()
+                        });
+
+                    self.heredocs.push((value.unescape(), strip_tabs, expand));
+                }
+            }
+            _ => {}
+        }
+
         Some(arg)
     }
 }
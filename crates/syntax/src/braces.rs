@@ -0,0 +1,226 @@
+use crate::common::enclosed_len;
+use core::str::CharIndices;
+use std::borrow::Cow;
+
+/// The most items a range expands to, so a typo like `{1..999999999}` can't exhaust memory.
+const MAX_RANGE: u64 = 10_000;
+
+/// A range of braces with more than 10,000 items.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RangeTooLong;
+
+/// Expand the braces of a word, `{a,b}` into `a b`, and `{1..3}` into `1 2 3`.
+///
+/// Braces nest, and expand left to right, so `{a,b}{1,2}` is `a1 a2 b1 b2`. Ranges are of
+/// integers or characters, with an optional step, `{a..z..2}`, and integers are zero-padded when
+/// either end is, `{01..10}`. Braces without a comma or range are left alone, as are those of
+/// `${...}`, and anything within `$(...)`, or quotes.
+///
+/// Fails if a range has more than 10,000 items.
+#[inline]
+pub(crate) fn expand(word: &str) -> Result<Vec<Cow<'_, str>>, RangeTooLong> {
+    let (open, close, items) = match find(word)? {
+        Some(found) => found,
+        None => return Ok(vec![Cow::Borrowed(word)]),
+    };
+
+    // SAFETY: `find` ensures both braces are on character boundaries.
+    let (preamble, postscript) =
+        unsafe { (word.get_unchecked(..open), word.get_unchecked(close + 1..)) };
+
+    let postscripts = expand(postscript)?;
+    let mut words = Vec::new();
+
+    for item in items {
+        for item in expand(&item)? {
+            for postscript in &postscripts {
+                words.push(Cow::Owned(format!("{preamble}{item}{postscript}")));
+            }
+        }
+    }
+
+    Ok(words)
+}
+
+/// Find the first braces which expand, returning the offsets of both braces and the items
+/// within.
+#[inline]
+fn find(word: &str) -> Result<Option<(usize, usize, Vec<Cow<'_, str>>)>, RangeTooLong> {
+    let mut iter = word.char_indices();
+
+    while let Some((index, character)) = iter.next() {
+        // SAFETY: `index` is always on a character boundary.
+        let rest = unsafe { word.get_unchecked(index..) };
+
+        match character {
+            '\\' => {
+                iter.next();
+            }
+            '\'' | '"' | '`' => skip_quoted(&mut iter, character),
+            // parameter expansion, or command substitution, skip all of it
+            '$' if rest[1..].starts_with(['{', '(']) => {
+                let (open, close) = if rest[1..].starts_with('{') {
+                    ('{', '}')
+                } else {
+                    ('(', ')')
+                };
+
+                let len = enclosed_len(&rest[1..], open, close).unwrap_or(rest.len() - 1);
+
+                while iter.offset() < index + 1 + len {
+                    iter.next();
+                }
+            }
+            '{' => {
+                let len = match enclosed_len(rest, '{', '}') {
+                    Some(len) => len,
+                    None => continue,
+                };
+
+                let body = &rest[1..len - 1];
+                let items = split_commas(body).map(Ok).or_else(|| sequence(body));
+
+                if let Some(items) = items {
+                    return Ok(Some((index, index + len - 1, items?)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(None)
+}
+
+/// Split the inside of braces on each comma which isn't nested, quoted, or escaped.
+///
+/// Returns `None` when there are no such commas.
+#[inline]
+fn split_commas(body: &str) -> Option<Vec<Cow<'_, str>>> {
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut iter = body.char_indices();
+
+    while let Some((index, character)) = iter.next() {
+        match character {
+            '\\' => {
+                iter.next();
+            }
+            '\'' | '"' | '`' => skip_quoted(&mut iter, character),
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(Cow::Borrowed(&body[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if items.is_empty() {
+        return None;
+    }
+
+    items.push(Cow::Borrowed(&body[start..]));
+
+    Some(items)
+}
+
+/// Expand a range, `x..y` or `x..y..step`, of integers or characters.
+///
+/// Returns `None` when `body` isn't a range.
+#[inline]
+fn sequence(body: &str) -> Option<Result<Vec<Cow<'_, str>>, RangeTooLong>> {
+    let mut parts = body.split("..");
+    let start = parts.next()?;
+    let end = parts.next()?;
+    let step = match parts.next() {
+        Some(step) => step.parse::<i64>().ok()?.unsigned_abs().max(1),
+        None => 1,
+    };
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    if let (Ok(first), Ok(last)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        let width = if is_padded(start) || is_padded(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+
+        let items = range(first, last, step).map(|range| {
+            range
+                .map(|integer| Cow::Owned(format!("{integer:0width$}")))
+                .collect()
+        });
+
+        return Some(items);
+    }
+
+    let (first, last) = match (single_char(start), single_char(end)) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return None,
+    };
+
+    let items = range(first as i64, last as i64, step).map(|range| {
+        range
+            .flat_map(|code| char::from_u32(code as u32))
+            .map(|character| Cow::Owned(String::from(character)))
+            .collect()
+    });
+
+    Some(items)
+}
+
+/// Iterate from `first` to `last` inclusive, counting down if `last` is smaller.
+///
+/// Fails when there are more than `MAX_RANGE` items.
+#[inline]
+fn range(first: i64, last: i64, step: u64) -> Result<impl Iterator<Item = i64>, RangeTooLong> {
+    let len = first.abs_diff(last) / step + 1;
+
+    if len > MAX_RANGE {
+        return Err(RangeTooLong);
+    }
+
+    let step = if first <= last {
+        step as i64
+    } else {
+        -(step as i64)
+    };
+
+    Ok((0..len as i64).map(move |index| first + index * step))
+}
+
+/// Does this integer have leading zeros, i.e. `01` or `-01`?
+#[inline]
+fn is_padded(integer: &str) -> bool {
+    let digits = integer.strip_prefix('-').unwrap_or(integer);
+
+    digits.len() > 1 && digits.starts_with('0')
+}
+
+/// Returns the character of a string containing exactly one ASCII letter.
+#[inline]
+fn single_char(string: &str) -> Option<char> {
+    let mut chars = string.chars();
+    let character = chars.next()?;
+
+    (chars.next().is_none() && character.is_ascii_alphabetic()).then_some(character)
+}
+
+/// Skip a quoted string, up to and including the closing `quote`.
+#[inline]
+fn skip_quoted(iter: &mut CharIndices<'_>, quote: char) {
+    while let Some((_index, character)) = iter.next() {
+        match character {
+            '\\' if quote != '\'' => {
+                iter.next();
+            }
+            character if character == quote => break,
+            _ => {}
+        }
+    }
+}
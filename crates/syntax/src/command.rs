@@ -1,4 +1,4 @@
-use crate::{Arg, Args, Quote, Value, Var, Vars};
+use crate::{Arg, Args, Operator, Quote, Redirect, Value, Var, Vars};
 
 #[derive(Clone, Debug)]
 pub enum CommandError<'a> {
@@ -11,16 +11,28 @@ pub struct Command<'a> {
     pub vars: Vec<Var<'a>>,
     pub program: Arg<'a>,
     pub args: Vec<Arg<'a>>,
+    pub redirects: Vec<Redirect<'a>>,
     pub offset: usize,
 }
 
 impl<'a> Command<'a> {
+    /// Is this command empty, i.e. a blank line?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+            && self.program.as_str().is_empty()
+            && self.program.quote().is_none()
+            && self.args.is_empty()
+            && self.redirects.is_empty()
+    }
+
     #[inline]
     pub fn try_parse(string: &'a str) -> Result<Self, CommandError> {
         let mut iter = Vars::new(string);
         let mut offset = 0;
         let mut vars = Vec::new();
         let mut args = Vec::new();
+        let mut redirects = Vec::new();
 
         while let Some(var) = iter.next() {
             match var {
@@ -43,25 +55,83 @@ impl<'a> Command<'a> {
         // SAFETY: `offset` is always on a character boundary and it is always a valid index
         let string = unsafe { string.get_unchecked(offset..string.len()) };
         let mut iter = Args::new(string);
+        let mut redirect: Option<Redirect> = None;
+        let mut arg_end = None;
+
+        loop {
+            let start = iter.offset();
+            let arg = match iter.next() {
+                Some(arg) => arg,
+                None => break,
+            };
 
-        while let Some(arg) = iter.next() {
             match arg {
-                Arg::Value(value) => match value {
-                    //Value::IncompleteQuoted(quote, val) => {
-                    //return Err(CommandError::IncompleteVar(quote, val))
-                    //}
-                    // Value::Quoted | Value::Word
-                    // we use _ => since rustc cries about _quote not being bound in
-                    // Value::Quoted(_quote, _val) | Value::Word(_val)
-                    _ => {
-                        offset = iter.offset();
-                        args.push(arg);
+                Arg::Value(_value) if redirect.is_none() => {
+                    offset = iter.offset();
+                    arg_end = Some(offset);
+                    args.push(arg);
+                }
+                Arg::Value(value) => {
+                    // SAFETY: match arm ensures there is a redirection.
+                    let mut redirect = unsafe { redirect.take().unwrap_unchecked() };
+
+                    redirect.target = Some(value);
+                    redirects.push(redirect);
+                    offset = iter.offset();
+                    arg_end = None;
+                }
+                Arg::Operator(operator) if operator.is_redirect() => {
+                    // a redirection without a target
+                    if let Some(redirect) = redirect.take() {
+                        redirects.push(redirect);
                     }
-                },
+
+                    // a number immediately preceding the operator is the file descriptor, `2>`
+                    let fd = match args.last() {
+                        Some(Arg::Value(Value::Word(word)))
+                            if arg_end == Some(start) && operator != Operator::AndGreat =>
+                        {
+                            word.parse().ok()
+                        }
+                        _ => None,
+                    };
+
+                    if fd.is_some() {
+                        args.pop();
+                    }
+
+                    offset = iter.offset();
+                    arg_end = None;
+                    redirect = Some(Redirect {
+                        fd,
+                        operator,
+                        whitespace: "",
+                        target: None,
+                        heredoc: None,
+                    });
+                }
+                Arg::Whitespace(whitespace) => {
+                    if let Some(redirect) = &mut redirect {
+                        redirect.whitespace = whitespace;
+                    }
+                }
+                Arg::Heredoc(heredoc) => {
+                    let redirect = redirects.iter_mut().find(|redirect| {
+                        redirect.operator.is_heredoc() && redirect.heredoc.is_none()
+                    });
+
+                    if let Some(redirect) = redirect {
+                        redirect.heredoc = Some(heredoc);
+                    }
+                }
                 _ => {}
             }
         }
 
+        if let Some(redirect) = redirect {
+            redirects.push(redirect);
+        }
+
         let program = if args.is_empty() {
             Arg::Value(Value::Word(""))
         } else {
@@ -72,6 +142,7 @@ impl<'a> Command<'a> {
             program,
             args,
             vars,
+            redirects,
             offset,
         };
 
@@ -1,4 +1,5 @@
-use crate::{Chars, Quote, Token, Value};
+use crate::{Chars, Heredoc, Operator, Quote, Token, Value};
+use core::hint;
 
 #[derive(Clone, Debug)]
 pub struct Common<'a> {
@@ -25,33 +26,47 @@ impl<'a> Common<'a> {
     }
 
     /// Consume a quoted string.
+    ///
+    /// Within double quotes, or backticks, a backslash escapes the next character, and within
+    /// double quotes, expansions are consumed whole. Nothing is escaped within single quotes.
     #[inline]
     pub fn next_string(&mut self, quote: Quote) -> Token<'a> {
-        let iter = &mut self.iter;
-        let start = iter.offset();
+        let start = self.iter.offset();
         let mut terminated = false;
 
-        while let Some(character) = iter.peek() {
+        while let Some(character) = self.iter.peek() {
             if character == quote.as_char() {
-                if !iter
-                    .peek_back()
-                    .map(|character| character == '\\')
-                    .unwrap_or(false)
-                {
-                    terminated = true;
+                terminated = true;
 
-                    break;
+                break;
+            }
+
+            match (quote, character) {
+                (Quote::Quote, _) => {}
+                (_, '\\') => {
+                    self.iter.next();
                 }
+                (Quote::DoubleQuote, '$') => {
+                    let open = self.iter.peek_nth(1);
+
+                    if let Some((open, close)) = open.zip(expansion_close(open)) {
+                        self.iter.next();
+                        self.next_enclosed(open, close);
+
+                        continue;
+                    }
+                }
+                _ => {}
             }
 
-            iter.next();
+            self.iter.next();
         }
 
-        let end = iter.offset();
+        let end = self.iter.offset();
         let string = unsafe { self.string.get_unchecked(start..end) };
 
         if terminated {
-            iter.next();
+            self.iter.next();
 
             Token::Value(Value::Quoted(quote, string))
         } else {
@@ -79,26 +94,299 @@ impl<'a> Common<'a> {
         Token::Whitespace(string)
     }
 
-    /// Consume a word.
+    /// Consume a comment, up to the end of the line.
     #[inline]
-    pub fn next_word(&mut self) -> Token<'a> {
+    pub fn next_comment(&mut self) -> Token<'a> {
         let iter = &mut self.iter;
         let start = iter.offset().saturating_sub(1);
 
         while let Some(character) = iter.peek() {
-            if Quote::from_char(character).is_some() || character.is_whitespace() {
+            if character == '\n' {
                 break;
-            } else {
+            }
+
+            iter.next();
+        }
+
+        let end = iter.offset();
+        let string = unsafe { self.string.get_unchecked(start..end) };
+
+        Token::Whitespace(string)
+    }
+
+    /// Consume whitespace, stopping after the end of the line.
+    #[inline]
+    pub fn next_line_whitespace(&mut self, character: char) -> Token<'a> {
+        let iter = &mut self.iter;
+        let start = iter.offset().saturating_sub(1);
+
+        if character != '\n' {
+            while let Some(character) = iter.peek() {
+                if !character.is_whitespace() {
+                    break;
+                }
+
                 iter.next();
+
+                if character == '\n' {
+                    break;
+                }
             }
         }
 
         let end = iter.offset();
         let string = unsafe { self.string.get_unchecked(start..end) };
 
+        Token::Whitespace(string)
+    }
+
+    /// Consume the body of a here-document, up to and including the line containing only
+    /// `delimiter`.
+    #[inline]
+    pub fn next_heredoc(&mut self, delimiter: &str, strip_tabs: bool, expand: bool) -> Heredoc<'a> {
+        let rest = self.iter.end();
+        let mut len = 0;
+        let mut heredoc = Heredoc {
+            body: rest,
+            strip_tabs,
+            expand,
+            terminated: false,
+        };
+
+        for line in rest.split_inclusive('\n') {
+            let content = line.strip_suffix('\n').unwrap_or(line);
+            let content = if strip_tabs {
+                content.trim_start_matches('\t')
+            } else {
+                content
+            };
+
+            if content == delimiter {
+                // SAFETY: `len` is the length of the preceding lines.
+                heredoc.body = unsafe { rest.get_unchecked(..len) };
+                heredoc.terminated = true;
+                len += line.len();
+
+                break;
+            }
+
+            len += line.len();
+        }
+
+        let end = self.iter.offset() + len;
+
+        while self.iter.offset() < end {
+            self.iter.next();
+        }
+
+        heredoc
+    }
+
+    /// Consume the braces of a parameter expansion, `${...}`, or the parentheses of a command
+    /// substitution, `$(...)`, after the `$`.
+    ///
+    /// Unterminated braces consume the rest of the string.
+    #[inline]
+    fn next_enclosed(&mut self, open: char, close: char) {
+        let iter = &mut self.iter;
+        let end = match enclosed_len(iter.end(), open, close) {
+            Some(len) => iter.offset() + len,
+            None => self.string.len(),
+        };
+
+        while iter.offset() < end {
+            iter.next();
+        }
+    }
+
+    /// Consume ANSI-C quoting, `$'...'`, after the `$`.
+    ///
+    /// Unterminated quotes consume the rest of the string.
+    #[inline]
+    fn next_ansi_c(&mut self) {
+        let iter = &mut self.iter;
+
+        iter.next();
+
+        while let Some(character) = iter.next() {
+            match character {
+                '\\' => {
+                    iter.next();
+                }
+                '\'' => break,
+                _ => {}
+            }
+        }
+    }
+
+    /// Consume the quoted strings, and unquoted text, joined to the value begun at `start`
+    /// without whitespace, returning them as one word, i.e. `--opt="a b"`, or `"$HOME"/bin`.
+    ///
+    /// Returns `first`, the value consumed, when nothing is joined to it.
+    #[inline]
+    pub fn next_joined(&mut self, start: usize, first: Token<'a>) -> Token<'a> {
+        let mut joined = false;
+
+        while let Some(character) = self.iter.peek() {
+            if character.is_whitespace() || Operator::is_start(character) {
+                break;
+            }
+
+            self.iter.next();
+            joined = true;
+
+            let token = match Quote::from_char(character) {
+                Some(quote) => self.next_string(quote),
+                None => self.next_word(),
+            };
+
+            // an unterminated quote consumed the rest
+            if matches!(token, Token::Value(Value::IncompleteQuoted(_, _))) {
+                break;
+            }
+        }
+
+        if !joined {
+            return first;
+        }
+
+        let end = self.iter.offset();
+        // SAFETY: `start` and `end` are always on a character boundary.
+        let string = unsafe { self.string.get_unchecked(start..end) };
+
         Token::Value(Value::Word(string))
     }
 
+    /// Consume unquoted text, up to a quote, whitespace, or an operator.
+    ///
+    /// A backslash escapes the next character, and expansions, and ANSI-C quoting, are consumed
+    /// whole.
+    #[inline]
+    pub fn next_word(&mut self) -> Token<'a> {
+        Token::Value(Value::Word(self.next_text(true)))
+    }
+
+    /// Consume unquoted text, up to a quote, or the end of a word when `word` is set.
+    #[inline]
+    pub(crate) fn next_text(&mut self, word: bool) -> &'a str {
+        let start = self.iter.offset().saturating_sub(1);
+        // the first character was consumed by the caller, the last one which wasn't escaped
+        let mut previous = self.iter.peek_back();
+
+        if previous == Some('\\') {
+            self.iter.next();
+            previous = None;
+        }
+
+        while let Some(character) = self.iter.peek() {
+            if previous == Some('$') {
+                if let Some(close) = expansion_close(Some(character)) {
+                    self.next_enclosed(character, close);
+                    previous = None;
+
+                    continue;
+                }
+
+                if character == '\'' {
+                    self.next_ansi_c();
+                    previous = None;
+
+                    continue;
+                }
+            }
+
+            let iter = &mut self.iter;
+
+            if character == '\\' {
+                iter.next();
+                iter.next();
+                previous = None;
+
+                continue;
+            }
+
+            if Quote::from_char(character).is_some()
+                || (word && (Operator::is_start(character) || character.is_whitespace()))
+            {
+                break;
+            }
+
+            iter.next();
+            previous = Some(character);
+        }
+
+        let end = self.iter.offset();
+
+        unsafe { self.string.get_unchecked(start..end) }
+    }
+
+    /// Consume an operator.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure `character` begins an operator.
+    #[inline]
+    pub unsafe fn next_operator(&mut self, character: char) -> Token<'a> {
+        let iter = &mut self.iter;
+        let operator = match (character, iter.peek()) {
+            ('|', Some('|')) => {
+                iter.next();
+
+                Operator::Or
+            }
+            ('|', _) => Operator::Pipe,
+            (';', _) => Operator::Semicolon,
+            ('<', Some('<')) => {
+                iter.next();
+
+                match iter.peek() {
+                    Some('<') => {
+                        iter.next();
+
+                        Operator::TripleLess
+                    }
+                    Some('-') => {
+                        iter.next();
+
+                        Operator::DoubleLessDash
+                    }
+                    _ => Operator::DoubleLess,
+                }
+            }
+            ('<', Some('&')) => {
+                iter.next();
+
+                Operator::LessAnd
+            }
+            ('<', _) => Operator::Less,
+            ('>', Some('>')) => {
+                iter.next();
+
+                Operator::DoubleGreat
+            }
+            ('>', Some('&')) => {
+                iter.next();
+
+                Operator::GreatAnd
+            }
+            ('>', _) => Operator::Great,
+            ('&', Some('&')) => {
+                iter.next();
+
+                Operator::And
+            }
+            ('&', Some('>')) => {
+                iter.next();
+
+                Operator::AndGreat
+            }
+            ('&', _) => Operator::Ampersand,
+            _ => hint::unreachable_unchecked(),
+        };
+
+        Token::Operator(operator)
+    }
+
     /*pub fn current(&mut self) -> Option<char> {
         self.iter.current()
     }*/
@@ -121,3 +409,46 @@ impl<'a> Iterator for Common<'a> {
         self.iter.next()
     }
 }
+
+/// Returns the closing character if `character` opens an expansion after `$`, i.e. `{` or `(`.
+#[inline]
+fn expansion_close(character: Option<char>) -> Option<char> {
+    match character {
+        Some('{') => Some('}'),
+        Some('(') => Some(')'),
+        _ => None,
+    }
+}
+
+/// Returns the length of the braces, or parentheses, at the start of `string`, including both
+/// ends, or `None` if they are never closed.
+///
+/// Anything inside quotes, or escaped by a backslash, is skipped.
+#[inline]
+pub(crate) fn enclosed_len(string: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut iter = string.char_indices();
+
+    while let Some((index, character)) = iter.next() {
+        match (quote, character) {
+            (Some(quote_char), character) if character == quote_char => quote = None,
+            (Some('"') | None, '\\') => {
+                iter.next();
+            }
+            (Some(_quote), _) => {}
+            (None, '\'' | '"') => quote = Some(character),
+            (None, character) if character == open => depth += 1,
+            (None, character) if character == close => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
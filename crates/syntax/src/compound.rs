@@ -0,0 +1,68 @@
+use crate::{Arg, Command, Redirect, Script, Statement, Value};
+
+/// `if condition; then body; elif condition; then body; else body; fi`
+#[derive(Clone, Debug)]
+pub struct If<'a> {
+    /// Each condition, along with the body run when it succeeds.
+    pub branches: Vec<(Script<'a>, Script<'a>)>,
+    /// The body run when no condition succeeds, `else`.
+    pub otherwise: Option<Script<'a>>,
+}
+
+/// `while condition; do body; done`, or `until condition; do body; done`
+#[derive(Clone, Debug)]
+pub struct While<'a> {
+    /// Loop while the condition fails instead, `until`.
+    pub until: bool,
+    pub condition: Script<'a>,
+    pub body: Script<'a>,
+}
+
+/// `for name in words; do body; done`
+#[derive(Clone, Debug)]
+pub struct For<'a> {
+    pub name: &'a str,
+    /// The words to loop over, or `None` for the positional parameters, when `in` is omitted.
+    pub words: Option<Vec<Arg<'a>>>,
+    pub body: Script<'a>,
+}
+
+/// `name() { body; }`, or `function name { body; }`
+#[derive(Clone, Debug)]
+pub struct Function<'a> {
+    pub name: &'a str,
+    pub body: Script<'a>,
+    /// The source of the body, within the braces, for the shell to keep once the script is gone.
+    pub source: &'a str,
+}
+
+/// `case word in pattern | pattern) body;; esac`
+#[derive(Clone, Debug)]
+pub struct Case<'a> {
+    pub word: Arg<'a>,
+    pub arms: Vec<CaseArm<'a>>,
+}
+
+/// A single arm of `case`, run when the word matches any of the glob patterns.
+#[derive(Clone, Debug)]
+pub struct CaseArm<'a> {
+    pub patterns: Vec<Value<'a>>,
+    pub body: Script<'a>,
+}
+
+/// A pipeline of which a command is compound, `for x in a b; do echo $x; done | wc -l`, or a lone
+/// compound command with redirections, `if a; then b; fi > file`.
+#[derive(Clone, Debug)]
+pub struct Piped<'a> {
+    pub elements: Vec<Element<'a>>,
+    /// The source of the pipeline.
+    pub string: &'a str,
+}
+
+/// A single command of a pipeline of which a command is compound.
+#[derive(Clone, Debug)]
+pub enum Element<'a> {
+    Command(Command<'a>),
+    /// A compound command, and the redirections following it.
+    Compound(Box<Statement<'a>>, Vec<Redirect<'a>>),
+}
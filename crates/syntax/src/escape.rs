@@ -0,0 +1,132 @@
+use crate::Quote;
+use core::iter::Peekable;
+use core::str::Chars;
+use std::borrow::Cow;
+
+/// Remove the escapes of a string within `quote`, or of a word when `None`.
+///
+/// Within a word, a backslash escapes any character, and `$'...'` is expanded as ANSI-C quoting.
+/// Within double quotes, or backticks, a backslash only escapes `$`, `` ` ``, `"` and `\`. A
+/// backslash before a newline is removed along with it, and nothing is escaped within single
+/// quotes.
+///
+/// When `glob` is set, escaped glob characters are kept escaped, so the result may be used as a
+/// pattern which matches them literally.
+#[inline]
+pub(crate) fn unescape(string: &str, quote: Option<Quote>, glob: bool) -> Cow<'_, str> {
+    let has_escapes = match quote {
+        Some(Quote::Quote) => false,
+        Some(_quote) => string.contains('\\'),
+        None => string.contains('\\') || string.contains("$'"),
+    };
+
+    if !has_escapes {
+        return Cow::Borrowed(string);
+    }
+
+    let mut unescaped = String::with_capacity(string.len());
+    let mut chars = string.chars().peekable();
+
+    while let Some(character) = chars.next() {
+        match (quote, character) {
+            (_, '\\') => match chars.next() {
+                Some('\n') => {}
+                Some(character @ ('$' | '`' | '"' | '\\')) => {
+                    push_escaped(&mut unescaped, character, glob)
+                }
+                Some(character) if quote.is_none() => push_escaped(&mut unescaped, character, glob),
+                Some(character) => {
+                    unescaped.push('\\');
+                    unescaped.push(character);
+                }
+                None => unescaped.push('\\'),
+            },
+            (None, '$') if chars.peek() == Some(&'\'') => {
+                chars.next();
+                ansi_c(&mut chars, &mut unescaped, glob);
+            }
+            (_, character) => unescaped.push(character),
+        }
+    }
+
+    Cow::Owned(unescaped)
+}
+
+/// Push a character which was escaped, escaping it again if it is special to globs.
+#[inline]
+fn push_escaped(string: &mut String, character: char, glob: bool) {
+    if glob && matches!(character, '*' | '?' | '[' | ']' | '\\') {
+        string.push('\\');
+    }
+
+    string.push(character);
+}
+
+/// Expand the inside of `$'...'`, up to and including the closing quote.
+///
+/// Supports `\a`, `\b`, `\e`, `\f`, `\n`, `\r`, `\t`, `\v`, `\\`, `\'`, `\"`, `\?`, octal `\nnn`,
+/// hexadecimal `\xHH`, unicode `\uHHHH` and `\UHHHHHHHH`, and control characters `\cX`.
+#[inline]
+fn ansi_c(chars: &mut Peekable<Chars<'_>>, string: &mut String, glob: bool) {
+    while let Some(character) = chars.next() {
+        let character = match character {
+            '\'' => break,
+            '\\' => match chars.next() {
+                Some('a') => '\x07',
+                Some('b') => '\x08',
+                Some('e' | 'E') => '\x1b',
+                Some('f') => '\x0c',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('v') => '\x0b',
+                Some(digit @ '0'..='7') => {
+                    let code = digit as u32 - '0' as u32;
+
+                    number(chars, code, 8, 2).unwrap_or(char::REPLACEMENT_CHARACTER)
+                }
+                Some('x') => number(chars, 0, 16, 2).unwrap_or(char::REPLACEMENT_CHARACTER),
+                Some('u') => number(chars, 0, 16, 4).unwrap_or(char::REPLACEMENT_CHARACTER),
+                Some('U') => number(chars, 0, 16, 8).unwrap_or(char::REPLACEMENT_CHARACTER),
+                Some('c') => match chars.next() {
+                    Some(control) => char::from(control as u8 & 0x1f),
+                    None => break,
+                },
+                Some(character @ ('\\' | '\'' | '"' | '?')) => character,
+                Some(character) => {
+                    string.push('\\');
+
+                    character
+                }
+                None => {
+                    string.push('\\');
+
+                    break;
+                }
+            },
+            character => {
+                string.push(character);
+
+                continue;
+            }
+        };
+
+        push_escaped(string, character, glob);
+    }
+}
+
+/// Parse up to `max` more digits in `radix`, continuing from `code`.
+#[inline]
+fn number(chars: &mut Peekable<Chars<'_>>, mut code: u32, radix: u32, max: usize) -> Option<char> {
+    for _ in 0..max {
+        match chars.peek().and_then(|digit| digit.to_digit(radix)) {
+            Some(digit) => {
+                code = code * radix + digit;
+                chars.next();
+            }
+            None => break,
+        }
+    }
+
+    char::from_u32(code)
+}
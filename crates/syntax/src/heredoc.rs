@@ -0,0 +1,32 @@
+use std::borrow::Cow;
+
+/// The body of a here-document, the lines following `<<DELIMITER` up to the line `DELIMITER`.
+#[derive(Clone, Copy, Debug)]
+pub struct Heredoc<'a> {
+    /// The lines of the body, excluding the delimiter line.
+    pub body: &'a str,
+    /// Remove leading tabs from each line, `<<-`.
+    pub strip_tabs: bool,
+    /// Expand parameters and commands within the body, as the delimiter was unquoted.
+    pub expand: bool,
+    /// Was the delimiter line found?
+    pub terminated: bool,
+}
+
+impl<'a> Heredoc<'a> {
+    /// Returns the text of the body, with leading tabs removed for `<<-`.
+    #[inline]
+    pub fn text(&self) -> Cow<'a, str> {
+        if !self.strip_tabs {
+            return Cow::Borrowed(self.body);
+        }
+
+        let text = self
+            .body
+            .split_inclusive('\n')
+            .map(|line| line.trim_start_matches('\t'))
+            .collect();
+
+        Cow::Owned(text)
+    }
+}
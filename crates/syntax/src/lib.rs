@@ -4,19 +4,47 @@
 pub(crate) use common::Common;
 
 pub use args::{Arg, Args};
+pub use braces::RangeTooLong;
 pub use chars::Chars;
 pub use command::{Command, CommandError};
+pub use compound::{Case, CaseArm, Element, For, Function, If, Piped, While};
+pub use heredoc::Heredoc;
+pub use list::List;
+pub use operator::Operator;
+pub use param::{Param, ParamOp};
+pub use parser::is_name;
+pub use parts::{Literal, Part, Parts};
+pub use pipeline::Pipeline;
+pub use programs::Programs;
 pub use quote::Quote;
+pub use redirect::Redirect;
+pub use script::{Script, ScriptError, Statement};
+pub use segments::Segments;
 pub use token::Token;
 pub use value::Value;
 pub use vars::{Var, Vars};
 
 mod args;
+mod braces;
 mod chars;
 mod command;
+mod compound;
+mod heredoc;
+mod list;
+mod operator;
+mod param;
+mod parser;
+mod parts;
+mod pipeline;
+mod programs;
 mod quote;
+mod redirect;
+mod script;
+mod segments;
 mod token;
 mod value;
 mod vars;
 
 pub(crate) mod common;
+pub(crate) mod escape;
+pub(crate) mod split;
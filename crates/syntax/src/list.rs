@@ -0,0 +1,73 @@
+use crate::split::Split;
+use crate::{Arg, Args, CommandError, Operator, Pipeline};
+
+#[derive(Clone, Debug)]
+pub struct List<'a> {
+    pub pipelines: Vec<Pipeline<'a>>,
+    /// The separators between each pipeline (`;`, `&`, `&&` or `||`), including surrounding
+    /// whitespace.
+    pub separators: Vec<(Operator, &'a str)>,
+}
+
+impl<'a> List<'a> {
+    #[inline]
+    pub fn try_parse(string: &'a str) -> Result<Self, CommandError<'a>> {
+        let mut pipelines = Vec::new();
+        let mut separators = Vec::new();
+
+        for (pipeline, separator) in Split::new(string, Operator::is_separator) {
+            pipelines.push(Pipeline::try_parse(pipeline)?);
+
+            if let Some(separator) = separator {
+                separators.push(separator);
+            }
+        }
+
+        let mut list = List {
+            pipelines,
+            separators,
+        };
+
+        // here-document bodies follow the line, so they're assigned to each `<<` in order
+        let mut heredocs = Args::new(string).filter_map(|arg| match arg {
+            Arg::Heredoc(heredoc) => Some(heredoc),
+            _ => None,
+        });
+
+        let redirects = list
+            .pipelines
+            .iter_mut()
+            .flat_map(|pipeline| &mut pipeline.commands)
+            .flat_map(|command| &mut command.redirects)
+            .filter(|redirect| redirect.operator.is_heredoc());
+
+        for redirect in redirects {
+            redirect.heredoc = heredocs.next();
+        }
+
+        Ok(list)
+    }
+
+    /// Does any here-document still need its body, or the line ending it?
+    #[inline]
+    pub fn needs_heredoc(&self) -> bool {
+        self.pipelines
+            .iter()
+            .flat_map(|pipeline| &pipeline.commands)
+            .flat_map(|command| &command.redirects)
+            .any(|redirect| redirect.needs_heredoc())
+    }
+
+    /// Returns the last pipeline of the list.
+    #[inline]
+    pub fn last(&self) -> &Pipeline<'a> {
+        // SAFETY: a list always contains at least one pipeline.
+        unsafe { self.pipelines.last().unwrap_unchecked() }
+    }
+
+    /// Is the pipeline at `index` followed by `&`, to run in the background?
+    #[inline]
+    pub fn is_background(&self, index: usize) -> bool {
+        matches!(self.separators.get(index), Some((Operator::Ampersand, _)))
+    }
+}
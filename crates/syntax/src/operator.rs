@@ -0,0 +1,117 @@
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operator {
+    /// `&`
+    Ampersand,
+    /// `&&`
+    And,
+    /// `&>`
+    AndGreat,
+    /// `>>`
+    DoubleGreat,
+    /// `<<`, a here-document.
+    DoubleLess,
+    /// `<<-`, a here-document with leading tabs removed.
+    DoubleLessDash,
+    /// `>`
+    Great,
+    /// `>&`
+    GreatAnd,
+    /// `<`
+    Less,
+    /// `<&`
+    LessAnd,
+    /// `||`
+    Or,
+    /// `|`
+    Pipe,
+    /// `;`
+    Semicolon,
+    /// `<<<`, a here-string.
+    TripleLess,
+}
+
+impl Operator {
+    /// Obtain the string value of the operator variant.
+    #[inline]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Ampersand => "&",
+            Operator::And => "&&",
+            Operator::AndGreat => "&>",
+            Operator::DoubleGreat => ">>",
+            Operator::DoubleLess => "<<",
+            Operator::DoubleLessDash => "<<-",
+            Operator::Great => ">",
+            Operator::GreatAnd => ">&",
+            Operator::Less => "<",
+            Operator::LessAnd => "<&",
+            Operator::Or => "||",
+            Operator::Pipe => "|",
+            Operator::Semicolon => ";",
+            Operator::TripleLess => "<<<",
+        }
+    }
+
+    /// Can `character` begin an operator?
+    #[inline]
+    pub const fn is_start(character: char) -> bool {
+        matches!(character, '|' | '<' | '>' | ';' | '&')
+    }
+
+    /// Is this operator a pipe?
+    #[inline]
+    pub const fn is_pipe(&self) -> bool {
+        matches!(self, Operator::Pipe)
+    }
+
+    /// Is this operator a list separator?
+    #[inline]
+    pub const fn is_separator(&self) -> bool {
+        matches!(
+            self,
+            Operator::Ampersand | Operator::And | Operator::Or | Operator::Semicolon
+        )
+    }
+
+    /// Is this operator a redirection?
+    #[inline]
+    pub const fn is_redirect(&self) -> bool {
+        matches!(
+            self,
+            Operator::AndGreat
+                | Operator::DoubleGreat
+                | Operator::DoubleLess
+                | Operator::DoubleLessDash
+                | Operator::Great
+                | Operator::GreatAnd
+                | Operator::Less
+                | Operator::LessAnd
+                | Operator::TripleLess
+        )
+    }
+
+    /// Is this operator a here-document, whose body follows on the next lines?
+    #[inline]
+    pub const fn is_heredoc(&self) -> bool {
+        matches!(self, Operator::DoubleLess | Operator::DoubleLessDash)
+    }
+
+    /// Is this operator a redirection which duplicates a file descriptor?
+    #[inline]
+    pub const fn is_dup(&self) -> bool {
+        matches!(self, Operator::GreatAnd | Operator::LessAnd)
+    }
+
+    /// Returns the file descriptor a redirection applies to when none is provided.
+    #[inline]
+    pub const fn default_fd(&self) -> u32 {
+        match self {
+            Operator::Less
+            | Operator::LessAnd
+            | Operator::DoubleLess
+            | Operator::DoubleLessDash
+            | Operator::TripleLess => 0,
+            _ => 1,
+        }
+    }
+}
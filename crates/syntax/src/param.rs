@@ -0,0 +1,220 @@
+use crate::common::enclosed_len;
+
+/// A parameter expansion, `$name` or `${...}`.
+#[derive(Clone, Copy, Debug)]
+pub struct Param<'a> {
+    pub name: &'a str,
+    pub op: ParamOp<'a>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ParamOp<'a> {
+    /// `$name` or `${name}`
+    Plain,
+    /// `${#name}`
+    Length,
+    /// `${name-word}`, or `${name:-word}` when `colon` is set.
+    Default { colon: bool, word: &'a str },
+    /// `${name=word}`, or `${name:=word}` when `colon` is set.
+    Assign { colon: bool, word: &'a str },
+    /// `${name?word}`, or `${name:?word}` when `colon` is set.
+    Error { colon: bool, word: &'a str },
+    /// `${name+word}`, or `${name:+word}` when `colon` is set.
+    Alternate { colon: bool, word: &'a str },
+    /// `${name#pattern}`, or `${name##pattern}` when `longest` is set.
+    RemovePrefix { longest: bool, pattern: &'a str },
+    /// `${name%pattern}`, or `${name%%pattern}` when `longest` is set.
+    RemoveSuffix { longest: bool, pattern: &'a str },
+    /// `${name/pattern/string}`, or `${name//pattern/string}` when `all` is set.
+    Replace {
+        all: bool,
+        pattern: &'a str,
+        string: &'a str,
+    },
+}
+
+impl<'a> Param<'a> {
+    /// Parse a parameter expansion from the start of `string`, which begins with `$`.
+    ///
+    /// Returns the parameter and the length of its source, or `None` if `string` doesn't begin
+    /// with a parameter expansion.
+    #[inline]
+    pub fn parse(string: &'a str) -> Option<(Self, usize)> {
+        let rest = string.strip_prefix('$')?;
+
+        if rest.starts_with('{') {
+            let end = enclosed_len(rest, '{', '}')?;
+            // SAFETY: `enclosed_len` ensures the braces are on character boundaries.
+            let inner = unsafe { rest.get_unchecked(1..end - 1) };
+            let param = Self::parse_braced(inner)?;
+
+            return Some((param, end + 1));
+        }
+
+        let len = name_len(rest, false);
+
+        if len == 0 {
+            return None;
+        }
+
+        // SAFETY: `name_len` only counts ASCII characters.
+        let name = unsafe { rest.get_unchecked(..len) };
+        let param = Param {
+            name,
+            op: ParamOp::Plain,
+        };
+
+        Some((param, len + 1))
+    }
+
+    /// Parse the inside of `${...}`.
+    #[inline]
+    fn parse_braced(inner: &'a str) -> Option<Self> {
+        if let Some(name) = inner.strip_prefix('#') {
+            if !name.is_empty() && name_len(name, true) == name.len() {
+                let op = ParamOp::Length;
+
+                return Some(Param { name, op });
+            }
+        }
+
+        let len = name_len(inner, true);
+
+        if len == 0 {
+            return None;
+        }
+
+        // SAFETY: `name_len` only counts ASCII characters.
+        let (name, rest) = unsafe { (inner.get_unchecked(..len), inner.get_unchecked(len..)) };
+        let (colon, rest) = match rest.strip_prefix(':') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+
+        let mut chars = rest.chars();
+        let op = match (colon, chars.next()) {
+            (false, None) => ParamOp::Plain,
+            (colon, Some('-')) => ParamOp::Default {
+                colon,
+                word: chars.as_str(),
+            },
+            (colon, Some('=')) => ParamOp::Assign {
+                colon,
+                word: chars.as_str(),
+            },
+            (colon, Some('?')) => ParamOp::Error {
+                colon,
+                word: chars.as_str(),
+            },
+            (colon, Some('+')) => ParamOp::Alternate {
+                colon,
+                word: chars.as_str(),
+            },
+            (false, Some('#')) => match chars.as_str().strip_prefix('#') {
+                Some(pattern) => ParamOp::RemovePrefix {
+                    longest: true,
+                    pattern,
+                },
+                None => ParamOp::RemovePrefix {
+                    longest: false,
+                    pattern: chars.as_str(),
+                },
+            },
+            (false, Some('%')) => match chars.as_str().strip_prefix('%') {
+                Some(pattern) => ParamOp::RemoveSuffix {
+                    longest: true,
+                    pattern,
+                },
+                None => ParamOp::RemoveSuffix {
+                    longest: false,
+                    pattern: chars.as_str(),
+                },
+            },
+            (false, Some('/')) => {
+                let (all, rest) = match chars.as_str().strip_prefix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, chars.as_str()),
+                };
+
+                let (pattern, string) = split_slash(rest);
+
+                ParamOp::Replace {
+                    all,
+                    pattern,
+                    string,
+                }
+            }
+            _ => return None,
+        };
+
+        Some(Param { name, op })
+    }
+
+    /// Returns the word of the default, assign, error and alternate forms.
+    #[inline]
+    pub const fn word(&self) -> Option<&'a str> {
+        match self.op {
+            ParamOp::Default { word, .. }
+            | ParamOp::Assign { word, .. }
+            | ParamOp::Error { word, .. }
+            | ParamOp::Alternate { word, .. } => Some(word),
+            _ => None,
+        }
+    }
+}
+
+/// Is `character` a special parameter, i.e. `?` in `$?`?
+#[inline]
+pub const fn is_special(character: char) -> bool {
+    matches!(
+        character,
+        '?' | '#' | '@' | '*' | '$' | '!' | '-' | '0'..='9'
+    )
+}
+
+/// Returns the length of the parameter name at the start of `string`.
+///
+/// Outside of braces, positional parameters are a single digit.
+#[inline]
+fn name_len(string: &str, braced: bool) -> usize {
+    let mut chars = string.chars();
+
+    match chars.next() {
+        Some('a'..='z' | 'A'..='Z' | '_') => {
+            1 + chars
+                .take_while(|character| character.is_ascii_alphanumeric() || *character == '_')
+                .count()
+        }
+        Some('0'..='9') if braced => {
+            1 + chars
+                .take_while(|character| character.is_ascii_digit())
+                .count()
+        }
+        Some(character) if is_special(character) => 1,
+        _ => 0,
+    }
+}
+
+/// Split at the first `/` which isn't escaped by a backslash.
+#[inline]
+fn split_slash(string: &str) -> (&str, &str) {
+    let mut iter = string.char_indices();
+
+    while let Some((index, character)) = iter.next() {
+        match character {
+            '\\' => {
+                iter.next();
+            }
+            // SAFETY: `/` is a single byte.
+            '/' => unsafe {
+                return (
+                    string.get_unchecked(..index),
+                    string.get_unchecked(index + 1..),
+                );
+            },
+            _ => {}
+        }
+    }
+
+    (string, "")
+}
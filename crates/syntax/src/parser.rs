@@ -0,0 +1,834 @@
+use crate::{
+    Arg, Args, Case, CaseArm, Command, Element, For, Function, Heredoc, If, List, Operator, Piped,
+    Redirect, Script, ScriptError, Statement, Value, Var, While,
+};
+use std::collections::VecDeque;
+
+/// Words which begin, or end compound commands, when they begin a command.
+const RESERVED: &[&str] = &[
+    "case", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "then", "until",
+    "while", "{", "}",
+];
+
+/// Words which begin compound commands that may follow `&&` or `||`.
+const COMPOUND: &[&str] = &["case", "for", "if", "until", "while"];
+
+/// Parses a script from its arguments, handing each run of simple commands to `List`.
+pub(crate) struct Parser<'a> {
+    string: &'a str,
+    /// Each argument, along with its start and end offsets.
+    args: Vec<(usize, usize, Arg<'a>)>,
+    index: usize,
+    /// The bodies of here-documents, in the order their redirections appear.
+    heredocs: VecDeque<Heredoc<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    #[inline]
+    pub fn new(string: &'a str) -> Self {
+        let mut iter = Args::new(string);
+        let mut args = Vec::new();
+        let mut heredocs = VecDeque::new();
+
+        loop {
+            let start = iter.offset();
+            let arg = match iter.next() {
+                Some(arg) => arg,
+                None => break,
+            };
+
+            if let Arg::Heredoc(heredoc) = arg {
+                heredocs.push_back(heredoc);
+            }
+
+            args.push((start, iter.offset(), arg));
+        }
+
+        Self {
+            string,
+            args,
+            index: 0,
+            heredocs,
+        }
+    }
+
+    /// Parse the whole string.
+    #[inline]
+    pub fn parse(mut self) -> Result<Script<'a>, ScriptError<'a>> {
+        self.script(&[]).map(|(script, _terminator)| script)
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&Arg<'a>> {
+        self.peek_nth(0)
+    }
+
+    #[inline]
+    fn peek_nth(&self, n: usize) -> Option<&Arg<'a>> {
+        self.args.get(self.index + n).map(|(_start, _end, arg)| arg)
+    }
+
+    /// Returns the next argument if it is an unquoted word.
+    #[inline]
+    fn peek_word(&self) -> Option<&'a str> {
+        match self.peek()? {
+            Arg::Value(Value::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    /// Is the next argument `;;`, ending an arm of `case`?
+    #[inline]
+    fn at_double_semicolon(&self) -> bool {
+        matches!(
+            (self.peek(), self.peek_nth(1)),
+            (
+                Some(Arg::Operator(Operator::Semicolon)),
+                Some(Arg::Operator(Operator::Semicolon))
+            )
+        )
+    }
+
+    /// Returns an error for the next argument, or for the end of the script when `expected` was.
+    #[inline]
+    fn unexpected(&self, expected: &'static str) -> ScriptError<'a> {
+        match self.peek() {
+            Some(arg) => ScriptError::Unexpected(arg.as_str()),
+            None => ScriptError::Incomplete(expected),
+        }
+    }
+
+    /// Skip whitespace within the line.
+    #[inline]
+    fn skip_whitespace(&mut self) {
+        while let Some(Arg::Whitespace(whitespace)) = self.peek() {
+            if ends_line(whitespace) {
+                break;
+            }
+
+            self.index += 1;
+        }
+    }
+
+    /// Skip whitespace, newlines, and `;`.
+    ///
+    /// The bodies of here-documents are skipped too, they belong to the redirections before them.
+    #[inline]
+    fn skip_separators(&mut self) {
+        loop {
+            match self.peek() {
+                Some(Arg::Whitespace(_) | Arg::Heredoc(_)) => {}
+                Some(Arg::Operator(Operator::Semicolon)) if !self.at_double_semicolon() => {}
+                _ => break,
+            }
+
+            self.index += 1;
+        }
+    }
+
+    /// Consume the word `word`, after any separators.
+    #[inline]
+    fn expect(&mut self, word: &'static str) -> Result<(), ScriptError<'a>> {
+        self.skip_separators();
+
+        if self.peek_word() == Some(word) {
+            self.index += 1;
+
+            Ok(())
+        } else {
+            Err(self.unexpected(word))
+        }
+    }
+
+    /// Parse statements up to one of `terminators`, consuming it, and returning which it was.
+    ///
+    /// The first terminator is the one reported as missing when the script ends.
+    #[inline]
+    fn script(
+        &mut self,
+        terminators: &[&'static str],
+    ) -> Result<(Script<'a>, &'static str), ScriptError<'a>> {
+        let mut statements = Vec::new();
+
+        loop {
+            self.skip_separators();
+
+            let next = if self.at_double_semicolon() {
+                Some(";;")
+            } else {
+                self.peek_word()
+            };
+
+            let terminator = terminators
+                .iter()
+                .find(|terminator| Some(**terminator) == next);
+
+            if let Some(terminator) = terminator {
+                self.index += if *terminator == ";;" { 2 } else { 1 };
+
+                return Ok((Script { statements }, *terminator));
+            }
+
+            if self.peek().is_none() {
+                return match terminators.first() {
+                    Some(terminator) => Err(ScriptError::Incomplete(terminator)),
+                    None => Ok((Script { statements }, "")),
+                };
+            }
+
+            statements.push(self.statement()?);
+
+            while let Some(operator) = self.and_or()? {
+                statements.push(Statement::AndOr(operator, Box::new(self.statement()?)));
+            }
+        }
+    }
+
+    /// Byte offset of the next argument, or the end of the script.
+    #[inline]
+    fn offset(&self) -> usize {
+        self.args
+            .get(self.index)
+            .map_or(self.string.len(), |arg| arg.0)
+    }
+
+    #[inline]
+    fn statement(&mut self) -> Result<Statement<'a>, ScriptError<'a>> {
+        let start = self.offset();
+
+        if let Some(statement) = self.compound()? {
+            return self.pipeline(start, Some(statement));
+        }
+
+        let statement = match self.peek_word() {
+            Some("function") => {
+                self.index += 1;
+                self.skip_whitespace();
+
+                match self.peek_word() {
+                    Some(name) => Statement::Function(self.function(name)?),
+                    None => return Err(self.unexpected("{")),
+                }
+            }
+            Some(name) if self.is_function() => Statement::Function(self.function(name)?),
+            Some(word) if RESERVED.contains(&word) => return Err(ScriptError::Unexpected(word)),
+            _ if self.at_double_semicolon() => return Err(ScriptError::Unexpected(";;")),
+            _ if self.pipes_into_compound() => return self.pipeline(start, None),
+            _ => return self.list().map(Statement::List),
+        };
+
+        // a function definition ends the command, pipes and redirections aren't supported
+        self.skip_whitespace();
+        self.end_of_statement(statement)
+    }
+
+    /// Parse a compound command, if the next word begins one.
+    #[inline]
+    fn compound(&mut self) -> Result<Option<Statement<'a>>, ScriptError<'a>> {
+        let statement = match self.peek_word() {
+            Some("if") => Statement::If(self.if_clause()?),
+            Some(keyword @ ("while" | "until")) => Statement::While(self.while_clause(keyword)?),
+            Some("for") => Statement::For(self.for_clause()?),
+            Some("case") => Statement::Case(self.case_clause()?),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(statement))
+    }
+
+    /// Returns `statement` if nothing but `;`, `&&`, `||`, or the end of the line follows it.
+    #[inline]
+    fn end_of_statement(&self, statement: Statement<'a>) -> Result<Statement<'a>, ScriptError<'a>> {
+        match self.peek() {
+            None
+            | Some(Arg::Whitespace(_))
+            | Some(Arg::Operator(Operator::Semicolon | Operator::And | Operator::Or)) => {
+                Ok(statement)
+            }
+            Some(arg) => Err(ScriptError::Unexpected(arg.as_str())),
+        }
+    }
+
+    /// Does the pipeline at the next word pipe into a compound command, `a | while b; do c; done`?
+    #[inline]
+    fn pipes_into_compound(&self) -> bool {
+        let mut piped = false;
+
+        for (_start, _end, arg) in &self.args[self.index..] {
+            match arg {
+                // the pipeline continues on the next line after `|`
+                Arg::Whitespace(whitespace) if ends_line(whitespace) && !piped => return false,
+                Arg::Whitespace(_) | Arg::Heredoc(_) => {}
+                Arg::Operator(Operator::Pipe) => piped = true,
+                Arg::Operator(operator) if operator.is_separator() => return false,
+                Arg::Value(Value::Word(word)) if piped && COMPOUND.contains(word) => return true,
+                _ => piped = false,
+            }
+        }
+
+        false
+    }
+
+    /// Parse a pipeline of which a command is compound, beginning with `first` when it was
+    /// parsed already, or a lone compound command with redirections.
+    #[inline]
+    fn pipeline(
+        &mut self,
+        start: usize,
+        first: Option<Statement<'a>>,
+    ) -> Result<Statement<'a>, ScriptError<'a>> {
+        let mut elements = Vec::new();
+        let mut first = first;
+
+        loop {
+            let element = match first.take() {
+                Some(statement) => Element::Compound(Box::new(statement), self.redirects()?),
+                None => match self.compound()? {
+                    Some(statement) => Element::Compound(Box::new(statement), self.redirects()?),
+                    None => match self.peek_word() {
+                        Some(word) if RESERVED.contains(&word) => {
+                            return Err(ScriptError::Unexpected(word))
+                        }
+                        _ => Element::Command(self.command()?),
+                    },
+                },
+            };
+
+            elements.push(element);
+            self.skip_whitespace();
+
+            if !matches!(self.peek(), Some(Arg::Operator(Operator::Pipe))) {
+                break;
+            }
+
+            self.index += 1;
+
+            while let Some(Arg::Whitespace(_)) = self.peek() {
+                self.index += 1;
+            }
+
+            if self.peek().is_none() {
+                return Err(ScriptError::Incomplete("command"));
+            }
+        }
+
+        let end = self.offset();
+
+        // SAFETY: `start` and `end` are always on a character boundary.
+        let string = unsafe { self.string.get_unchecked(start..end) }.trim_end();
+
+        let lone = matches!(
+            elements.as_slice(),
+            [Element::Compound(_statement, redirects)] if redirects.is_empty()
+        );
+
+        let statement = match elements.pop() {
+            // without redirections, it's the compound command itself
+            Some(Element::Compound(statement, _redirects)) if lone => *statement,
+            element => {
+                elements.extend(element);
+
+                Statement::Pipeline(Piped { elements, string })
+            }
+        };
+
+        self.end_of_statement(statement)
+    }
+
+    /// Parse the redirections following a compound command, up to the next `|`, or the end of
+    /// the pipeline.
+    #[inline]
+    fn redirects(&mut self) -> Result<Vec<Redirect<'a>>, ScriptError<'a>> {
+        let command = self.command()?;
+
+        if let Some(Var::Pair(name, _value)) = command.vars.first() {
+            return Err(ScriptError::Unexpected(name));
+        }
+
+        match command.program.as_str() {
+            "" => Ok(command.redirects),
+            program => Err(ScriptError::Unexpected(program)),
+        }
+    }
+
+    /// Parse a simple command up to the next `|`, or the end of the pipeline.
+    #[inline]
+    fn command(&mut self) -> Result<Command<'a>, ScriptError<'a>> {
+        let start = self.offset();
+        let mut end = start;
+
+        while let Some((_arg_start, arg_end, arg)) = self.args.get(self.index) {
+            match arg {
+                Arg::Whitespace(whitespace) if ends_line(whitespace) => break,
+                Arg::Operator(operator) if operator.is_pipe() || operator.is_separator() => break,
+                Arg::Whitespace(_) => {}
+                _ => end = *arg_end,
+            }
+
+            self.index += 1;
+        }
+
+        self.check_end(false)?;
+
+        // SAFETY: `start` and `end` are always on a character boundary.
+        let string = unsafe { self.string.get_unchecked(start..end) };
+        let mut command = Command::try_parse(string).map_err(ScriptError::Command)?;
+
+        self.attach_heredocs(&mut command.redirects)?;
+
+        Ok(command)
+    }
+
+    /// Consume `&&` or `||` following a statement, and the whitespace, or newlines, after it.
+    #[inline]
+    fn and_or(&mut self) -> Result<Option<Operator>, ScriptError<'a>> {
+        self.skip_whitespace();
+
+        let operator = match self.peek() {
+            Some(Arg::Operator(operator @ (Operator::And | Operator::Or))) => *operator,
+            _ => return Ok(None),
+        };
+
+        self.index += 1;
+
+        while let Some(Arg::Whitespace(_)) = self.peek() {
+            self.index += 1;
+        }
+
+        match self.peek() {
+            Some(_arg) => Ok(Some(operator)),
+            None => Err(ScriptError::Incomplete("command")),
+        }
+    }
+
+    /// Parse simple commands up to the end of the line, or a reserved word beginning a command.
+    #[inline]
+    fn list(&mut self) -> Result<List<'a>, ScriptError<'a>> {
+        let first = self.index;
+        let start = self.args[first].0;
+        let mut end = self.string.len();
+        // does the next word begin a command, following `;` or `&`?
+        let mut command_start = true;
+        // does the line end with `|`, `&&` or `||`, so the list continues on the next one?
+        let mut continues = false;
+        // the index of `&&` or `||` before the next word, which may begin a compound command
+        let mut and_or = None;
+        // the index of the separator before the pipeline, and does the next word follow `|`?
+        let mut separator = None;
+        let mut piped = false;
+
+        while let Some((arg_start, arg_end, arg)) = self.args.get(self.index) {
+            let (arg_start, arg_end) = (*arg_start, *arg_end);
+            let heredoc_follows = matches!(self.peek_nth(1), Some(Arg::Heredoc(_)));
+
+            match arg {
+                // here-document bodies follow the newline
+                Arg::Whitespace(whitespace)
+                    if ends_line(whitespace) && !heredoc_follows && !continues =>
+                {
+                    end = arg_start;
+
+                    break;
+                }
+                Arg::Heredoc(_heredoc) if !heredoc_follows => {
+                    end = arg_end;
+                    self.index += 1;
+
+                    break;
+                }
+                Arg::Operator(Operator::Semicolon) if self.at_double_semicolon() => {
+                    end = arg_start;
+
+                    break;
+                }
+                Arg::Operator(operator) => {
+                    command_start = matches!(operator, Operator::Semicolon | Operator::Ampersand);
+                    continues = matches!(operator, Operator::Pipe | Operator::And | Operator::Or);
+                    and_or = matches!(operator, Operator::And | Operator::Or).then_some(self.index);
+                    piped = operator.is_pipe();
+
+                    if operator.is_separator() {
+                        separator = Some(self.index);
+                    }
+                }
+                // the pipeline into the compound command is a statement of its own
+                Arg::Value(Value::Word(word)) if piped && RESERVED.contains(word) => {
+                    let Some(index) = separator.filter(|_index| COMPOUND.contains(word)) else {
+                        return Err(ScriptError::Unexpected(word));
+                    };
+
+                    // `&&`, and `||` are left to precede the statement, `;`, and `&` end the list
+                    if matches!(
+                        self.args[index].2,
+                        Arg::Operator(Operator::And | Operator::Or)
+                    ) {
+                        self.index = index;
+                        end = self.args[index].0;
+                    } else {
+                        self.index = index + 1;
+                        end = self.args[index].1;
+                    }
+
+                    break;
+                }
+                // the compound command is a statement of its own, following the operator
+                Arg::Value(Value::Word(word)) if and_or.is_some() && COMPOUND.contains(word) => {
+                    // SAFETY: match guard ensures there is an operator.
+                    self.index = unsafe { and_or.unwrap_unchecked() };
+                    end = self.args[self.index].0;
+
+                    break;
+                }
+                Arg::Value(Value::Word(word)) if command_start && RESERVED.contains(word) => {
+                    if self.index == first {
+                        return Err(ScriptError::Unexpected(word));
+                    }
+
+                    end = arg_start;
+
+                    break;
+                }
+                Arg::Whitespace(_) | Arg::Heredoc(_) => {}
+                Arg::Value(_value) => {
+                    command_start = false;
+                    continues = false;
+                    and_or = None;
+                    piped = false;
+                }
+            }
+
+            self.index += 1;
+        }
+
+        self.check_end(continues)?;
+
+        // SAFETY: `start` and `end` are always on a character boundary.
+        let string = unsafe { self.string.get_unchecked(start..end) };
+        let mut list = List::try_parse(string.trim_end()).map_err(ScriptError::Command)?;
+
+        let redirects = list
+            .pipelines
+            .iter_mut()
+            .flat_map(|pipeline| &mut pipeline.commands)
+            .flat_map(|command| &mut command.redirects);
+
+        self.attach_heredocs(redirects)?;
+
+        Ok(list)
+    }
+
+    /// Fail if the script ended with an operator, quote, or line continuation left open, as it
+    /// needs more lines. `continues` is whether it ended with an operator.
+    #[inline]
+    fn check_end(&self, continues: bool) -> Result<(), ScriptError<'a>> {
+        if self.index != self.args.len() {
+            return Ok(());
+        }
+
+        if continues {
+            return Err(ScriptError::Incomplete("command"));
+        }
+
+        let last = self.args.last().and_then(|(_start, _end, arg)| arg.value());
+
+        if let Some(quote) = last.and_then(|value| value.open_quote()) {
+            return Err(ScriptError::Incomplete(quote.as_str()));
+        }
+
+        if let Some(Value::Word(word)) = last {
+            if ends_with_escape(word) {
+                return Err(ScriptError::Incomplete("\\"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Give each here-document of `redirects` its body, in order.
+    ///
+    /// Here-documents are parsed from the whole script, as the command may not contain them.
+    #[inline]
+    fn attach_heredocs<'r, I>(&mut self, redirects: I) -> Result<(), ScriptError<'a>>
+    where
+        'a: 'r,
+        I: IntoIterator<Item = &'r mut Redirect<'a>>,
+    {
+        let redirects = redirects
+            .into_iter()
+            .filter(|redirect| redirect.operator.is_heredoc());
+
+        for redirect in redirects {
+            let delimiter = match &redirect.target {
+                Some(target) => target.as_str(),
+                None => return Err(ScriptError::Unexpected(redirect.operator.as_str())),
+            };
+
+            redirect.heredoc = self.heredocs.pop_front();
+
+            if redirect.needs_heredoc() {
+                return Err(ScriptError::Incomplete(delimiter));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn if_clause(&mut self) -> Result<If<'a>, ScriptError<'a>> {
+        self.index += 1;
+
+        let mut branches = Vec::new();
+
+        loop {
+            let (condition, _then) = self.script(&["then"])?;
+            let (body, terminator) = self.script(&["fi", "elif", "else"])?;
+
+            branches.push((condition, body));
+
+            let otherwise = match terminator {
+                "elif" => continue,
+                "else" => Some(self.script(&["fi"])?.0),
+                _ => None,
+            };
+
+            return Ok(If {
+                branches,
+                otherwise,
+            });
+        }
+    }
+
+    #[inline]
+    fn while_clause(&mut self, keyword: &str) -> Result<While<'a>, ScriptError<'a>> {
+        self.index += 1;
+
+        let (condition, _do) = self.script(&["do"])?;
+        let (body, _done) = self.script(&["done"])?;
+
+        Ok(While {
+            until: keyword == "until",
+            condition,
+            body,
+        })
+    }
+
+    #[inline]
+    fn for_clause(&mut self) -> Result<For<'a>, ScriptError<'a>> {
+        self.index += 1;
+        self.skip_whitespace();
+
+        let name = match self.peek_word() {
+            Some(name) if is_name(name) => name,
+            _ => return Err(self.unexpected("do")),
+        };
+
+        self.index += 1;
+        self.skip_separators();
+
+        let words = if self.peek_word() == Some("in") {
+            let mut words = Vec::new();
+
+            self.index += 1;
+
+            loop {
+                match self.peek() {
+                    Some(Arg::Whitespace(whitespace)) if ends_line(whitespace) => break,
+                    Some(Arg::Operator(Operator::Semicolon)) | None => break,
+                    Some(Arg::Whitespace(_)) => {}
+                    Some(arg @ Arg::Value(_)) => words.push(arg.clone()),
+                    Some(arg) => return Err(ScriptError::Unexpected(arg.as_str())),
+                }
+
+                self.index += 1;
+            }
+
+            Some(words)
+        } else {
+            None
+        };
+
+        self.expect("do")?;
+
+        let (body, _done) = self.script(&["done"])?;
+
+        Ok(For { name, words, body })
+    }
+
+    #[inline]
+    fn case_clause(&mut self) -> Result<Case<'a>, ScriptError<'a>> {
+        self.index += 1;
+        self.skip_whitespace();
+
+        let word = match self.peek() {
+            Some(arg @ Arg::Value(_)) => arg.clone(),
+            _ => return Err(self.unexpected("in")),
+        };
+
+        self.index += 1;
+        self.expect("in")?;
+
+        let mut arms = Vec::new();
+
+        loop {
+            self.skip_separators();
+
+            if self.peek_word() == Some("esac") {
+                self.index += 1;
+
+                break;
+            }
+
+            let patterns = self.patterns()?;
+            let (body, terminator) = self.script(&["esac", ";;"])?;
+
+            arms.push(CaseArm { patterns, body });
+
+            if terminator == "esac" {
+                break;
+            }
+        }
+
+        Ok(Case { word, arms })
+    }
+
+    /// Is the next word the start of a function definition, `name()`, or `name ()`, either
+    /// perhaps followed directly by the brace, `name(){`?
+    #[inline]
+    fn is_function(&self) -> bool {
+        let word = match self.peek_word() {
+            Some(word) => word,
+            None => return false,
+        };
+
+        if let Some((name, _brace)) = strip_parens(word) {
+            return is_name(name);
+        }
+
+        let next = match self.peek_nth(1) {
+            Some(Arg::Whitespace(whitespace)) if !ends_line(whitespace) => self.peek_nth(2),
+            next => next,
+        };
+
+        is_name(word) && matches!(next, Some(Arg::Value(Value::Word("()" | "(){"))))
+    }
+
+    /// Parse a function definition from its name onward, `name() { body; }`.
+    #[inline]
+    fn function(&mut self, name: &'a str) -> Result<Function<'a>, ScriptError<'a>> {
+        let (name, mut brace) = strip_parens(name).unwrap_or((name, false));
+
+        if !is_name(name) {
+            return Err(ScriptError::Unexpected(name));
+        }
+
+        self.index += 1;
+
+        if !brace {
+            self.skip_whitespace();
+
+            if let Some(parens @ ("()" | "(){")) = self.peek_word() {
+                self.index += 1;
+                brace = parens == "(){";
+            }
+        }
+
+        if !brace {
+            self.expect("{")?;
+        }
+
+        let start = match self.args.get(self.index) {
+            Some((start, _end, _arg)) => *start,
+            None => self.string.len(),
+        };
+
+        let (body, _brace) = self.script(&["}"])?;
+        // the closing brace was just consumed
+        let end = self.args[self.index - 1].0;
+        // SAFETY: `start` and `end` are always on a character boundary.
+        let source = unsafe { self.string.get_unchecked(start..end) };
+
+        Ok(Function { name, body, source })
+    }
+
+    /// Parse the patterns of an arm of `case`, `(a | b)`, up to and including the closing
+    /// parenthesis.
+    #[inline]
+    fn patterns(&mut self) -> Result<Vec<Value<'a>>, ScriptError<'a>> {
+        let mut patterns = Vec::new();
+
+        loop {
+            let arg = match self.peek() {
+                Some(arg) => arg.clone(),
+                None => return Err(ScriptError::Incomplete("esac")),
+            };
+
+            self.index += 1;
+
+            match arg {
+                Arg::Whitespace(_) | Arg::Operator(Operator::Pipe) => {}
+                Arg::Value(Value::Word(word)) => {
+                    let word = if patterns.is_empty() {
+                        word.strip_prefix('(').unwrap_or(word)
+                    } else {
+                        word
+                    };
+
+                    let (pattern, closed) = match word.strip_suffix(')') {
+                        Some(pattern) => (pattern, true),
+                        None => (word, false),
+                    };
+
+                    if !pattern.is_empty() {
+                        patterns.push(Value::Word(pattern));
+                    }
+
+                    if closed {
+                        return Ok(patterns);
+                    }
+                }
+                Arg::Value(value) => patterns.push(value),
+                arg => return Err(ScriptError::Unexpected(arg.as_str())),
+            }
+        }
+    }
+}
+
+/// Is `string` a valid variable name?
+#[inline]
+pub fn is_name(string: &str) -> bool {
+    let mut chars = string.chars();
+
+    matches!(chars.next(), Some('a'..='z' | 'A'..='Z' | '_'))
+        && chars.all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
+/// Does `whitespace` end the line, rather than only continue it with a backslash?
+#[inline]
+fn ends_line(whitespace: &str) -> bool {
+    whitespace
+        .strip_prefix("\\\n")
+        .unwrap_or(whitespace)
+        .contains('\n')
+}
+
+/// Does `word` end with a backslash which isn't itself escaped?
+#[inline]
+fn ends_with_escape(word: &str) -> bool {
+    let backslashes = word
+        .chars()
+        .rev()
+        .take_while(|character| *character == '\\')
+        .count();
+
+    backslashes % 2 == 1
+}
+
+/// Strip the parentheses of a function definition from `word`, `name()`, or `name(){`,
+/// returning the name, and whether the opening brace followed.
+#[inline]
+fn strip_parens(word: &str) -> Option<(&str, bool)> {
+    match word.strip_suffix("(){") {
+        Some(name) => Some((name, true)),
+        None => word.strip_suffix("()").map(|name| (name, false)),
+    }
+}
@@ -0,0 +1,185 @@
+use crate::common::enclosed_len;
+use crate::escape::unescape;
+use crate::{List, Param, Quote, Segments};
+use std::borrow::Cow;
+
+/// A part of a value, either literal text or an expansion.
+#[derive(Clone, Debug)]
+pub enum Part<'a> {
+    Literal(Literal<'a>),
+    Param(Param<'a>),
+    /// Command substitution, `$(...)`.
+    Command(List<'a>),
+}
+
+impl<'a> Part<'a> {
+    /// Parse an expansion from the start of `string`, which begins with `$`.
+    ///
+    /// Returns the expansion and the length of its source, or `None` if `string` doesn't begin
+    /// with an expansion.
+    #[inline]
+    pub fn parse(string: &'a str) -> Option<(Self, usize)> {
+        let rest = string.strip_prefix('$')?;
+
+        if rest.starts_with('(') {
+            let end = enclosed_len(rest, '(', ')')?;
+            // SAFETY: `enclosed_len` ensures the parentheses are on character boundaries.
+            let inner = unsafe { rest.get_unchecked(1..end - 1) };
+            let list = List::try_parse(inner).ok()?;
+
+            return Some((Part::Command(list), end + 1));
+        }
+
+        let (param, len) = Param::parse(string)?;
+
+        Some((Part::Param(param), len))
+    }
+}
+
+/// Literal text, still escaped as it was written.
+#[derive(Clone, Copy, Debug)]
+pub struct Literal<'a> {
+    string: &'a str,
+    /// The quotes the text is within, `None` for a word.
+    quote: Option<Quote>,
+}
+
+impl<'a> Literal<'a> {
+    /// Returns the text as it was written.
+    #[inline]
+    pub const fn as_str(&self) -> &'a str {
+        self.string
+    }
+
+    /// Returns the text with its escapes removed.
+    #[inline]
+    pub fn unescape(&self) -> Cow<'a, str> {
+        unescape(self.string, self.quote, false)
+    }
+
+    /// Returns the text with its escapes removed, except for those of glob characters, for use
+    /// as a pattern.
+    #[inline]
+    pub fn pattern(&self) -> Cow<'a, str> {
+        unescape(self.string, self.quote, true)
+    }
+}
+
+/// Split a value into literal text and expansions.
+#[derive(Clone, Debug)]
+pub struct Parts<'a> {
+    string: &'a str,
+    /// The quotes the value is within, `None` for a word. Nothing is expanded within single
+    /// quotes.
+    quote: Option<Quote>,
+    /// The quoted strings, and unquoted text, of a word which follow `string`.
+    segments: Option<Segments<'a>>,
+}
+
+impl<'a> Parts<'a> {
+    /// Split `string`, a word, into literal text and expansions, including those of the
+    /// quoted strings it joins.
+    #[inline]
+    pub fn new(string: &'a str) -> Self {
+        Self {
+            string: "",
+            quote: None,
+            segments: Some(Segments::new(string)),
+        }
+    }
+
+    /// Split `string`, the inside of `quote`, into literal text and expansions.
+    #[inline]
+    pub const fn quoted(quote: Quote, string: &'a str) -> Self {
+        Self {
+            string,
+            quote: Some(quote),
+            segments: None,
+        }
+    }
+
+    /// Treat all of `string` as literal text.
+    #[inline]
+    pub const fn literal(string: &'a str) -> Self {
+        Self::quoted(Quote::Quote, string)
+    }
+
+    #[inline]
+    fn literal_part(&self, string: &'a str) -> Part<'a> {
+        Part::Literal(Literal {
+            string,
+            quote: self.quote,
+        })
+    }
+}
+
+impl<'a> Iterator for Parts<'a> {
+    type Item = Part<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Part<'a>> {
+        while self.string.is_empty() {
+            let segment = self.segments.as_mut()?.next()?;
+
+            self.string = segment.as_str();
+            self.quote = segment.quote();
+        }
+
+        let string = self.string;
+
+        if let Some(Quote::Quote) = self.quote {
+            self.string = "";
+
+            return Some(self.literal_part(string));
+        }
+
+        if let Some((part, len)) = Part::parse(string) {
+            // SAFETY: `len` is always on a character boundary.
+            self.string = unsafe { string.get_unchecked(len..) };
+
+            return Some(part);
+        }
+
+        // literal text up to the next expansion, skipping the `$` at the start which didn't begin
+        // one, any escaped by a backslash, and ANSI-C quoting in words
+        let mut iter = string.char_indices();
+        let mut end = string.len();
+
+        while let Some((index, character)) = iter.next() {
+            // SAFETY: `index` is always on a character boundary.
+            let rest = unsafe { string.get_unchecked(index..) };
+
+            match character {
+                '\\' => {
+                    iter.next();
+                }
+                '$' if self.quote.is_none() && rest.starts_with("$'") => {
+                    iter.next();
+
+                    while let Some((_index, character)) = iter.next() {
+                        match character {
+                            '\\' => {
+                                iter.next();
+                            }
+                            '\'' => break,
+                            _ => {}
+                        }
+                    }
+                }
+                '$' if index != 0 && Part::parse(rest).is_some() => {
+                    end = index;
+
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        // SAFETY: `end` is always on a character boundary.
+        let (literal, rest) = unsafe { (string.get_unchecked(..end), string.get_unchecked(end..)) };
+
+        self.string = rest;
+
+        Some(self.literal_part(literal))
+    }
+}
@@ -0,0 +1,46 @@
+use crate::split::Split;
+use crate::{Command, CommandError, Operator};
+
+#[derive(Clone, Debug)]
+pub struct Pipeline<'a> {
+    pub commands: Vec<Command<'a>>,
+    /// The pipes between each command, including surrounding whitespace.
+    pub pipes: Vec<&'a str>,
+    /// The source of the pipeline.
+    pub string: &'a str,
+}
+
+impl<'a> Pipeline<'a> {
+    #[inline]
+    pub fn try_parse(string: &'a str) -> Result<Self, CommandError<'a>> {
+        let mut commands = Vec::new();
+        let mut pipes = Vec::new();
+
+        for (command, pipe) in Split::new(string, Operator::is_pipe) {
+            commands.push(Command::try_parse(command)?);
+
+            if let Some((_operator, pipe)) = pipe {
+                pipes.push(pipe);
+            }
+        }
+
+        Ok(Pipeline {
+            commands,
+            pipes,
+            string,
+        })
+    }
+
+    /// Returns the last command of the pipeline.
+    #[inline]
+    pub fn last(&self) -> &Command<'a> {
+        // SAFETY: a pipeline always contains at least one command.
+        unsafe { self.commands.last().unwrap_unchecked() }
+    }
+
+    /// Is this pipeline a lone empty command?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.commands.len() == 1 && self.last().is_empty()
+    }
+}
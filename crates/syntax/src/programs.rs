@@ -0,0 +1,105 @@
+use crate::parser::is_name;
+use crate::{Arg, Args, Operator, Value};
+
+/// Words after which another command begins.
+const BEFORE_COMMAND: &[&str] = &[
+    "!", "do", "elif", "else", "if", "then", "until", "while", "{",
+];
+
+/// Words after which no command begins until the next separator.
+const NOT_COMMAND: &[&str] = &["case", "done", "esac", "fi", "for", "function", "}"];
+
+/// Iterate the unquoted words of a string which are run as programs, along with their offsets.
+///
+/// A program is the first word of each command, after any assignments, so in
+/// `FOO=1 ls | grep x && if true; then ll; fi`, they are `ls`, `grep`, `true` and `ll`. Reserved
+/// words, quoted words, and words joined to quotes, as in `l"s"`, are never programs.
+#[derive(Clone, Debug)]
+pub struct Programs<'a> {
+    iter: Args<'a>,
+    /// Is the next word in command position?
+    command: bool,
+    /// Is the next word the target of a redirection?
+    target: bool,
+    /// Is the current word an assignment, whose value may continue in quotes?
+    assignment: bool,
+}
+
+impl<'a> Programs<'a> {
+    #[inline]
+    pub fn new(string: &'a str) -> Self {
+        let iter = Args::new(string);
+
+        Self {
+            iter,
+            command: true,
+            target: false,
+            assignment: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Programs<'a> {
+    type Item = (usize, &'a str);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.iter.offset();
+            let arg = self.iter.next()?;
+
+            if !matches!(arg, Arg::Value(_)) {
+                self.assignment = false;
+            }
+
+            match arg {
+                Arg::Whitespace(whitespace) => {
+                    if whitespace.contains('\n') {
+                        self.command = true;
+                    }
+                }
+                Arg::Heredoc(_heredoc) => self.command = true,
+                Arg::Operator(operator) if operator.is_redirect() => self.target = true,
+                Arg::Operator(
+                    Operator::Ampersand
+                    | Operator::And
+                    | Operator::Or
+                    | Operator::Pipe
+                    | Operator::Semicolon,
+                ) => self.command = true,
+                Arg::Operator(_operator) => {}
+                Arg::Value(_value) if self.assignment => {}
+                Arg::Value(_value) if self.target => self.target = false,
+                Arg::Value(Value::Word(word)) if self.command => {
+                    if BEFORE_COMMAND.contains(&word) {
+                        continue;
+                    }
+
+                    if NOT_COMMAND.contains(&word) {
+                        self.command = false;
+
+                        continue;
+                    }
+
+                    if let Some((name, _value)) = word.split_once('=') {
+                        if is_name(name) {
+                            self.assignment = true;
+
+                            continue;
+                        }
+                    }
+
+                    self.command = false;
+
+                    // a word joined to a quoted value is only part of the program
+                    if let Some(Arg::Value(_value)) = self.iter.clone().next() {
+                        continue;
+                    }
+
+                    return Some((start, word));
+                }
+                Arg::Value(_value) => self.command = false,
+            }
+        }
+    }
+}
@@ -18,6 +18,16 @@ impl Quote {
         }
     }
 
+    /// Obtain the quote as a string.
+    #[inline]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Quote::Backtick => "`",
+            Quote::DoubleQuote => "\"",
+            Quote::Quote => "'",
+        }
+    }
+
     /// Construct a variant from a character.
     #[inline]
     pub const fn from_char(quote: char) -> Option<Self> {
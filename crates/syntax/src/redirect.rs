@@ -0,0 +1,47 @@
+use crate::{Heredoc, Operator, Value};
+
+#[derive(Clone, Debug)]
+pub struct Redirect<'a> {
+    /// The file descriptor to redirect, if provided, i.e. `2` in `2>&1`.
+    pub fd: Option<u32>,
+    pub operator: Operator,
+    /// Whitespace between the operator and the target.
+    pub whitespace: &'a str,
+    /// The file (or file descriptor) to redirect to, if present.
+    pub target: Option<Value<'a>>,
+    /// The body of a here-document, `<<`, once its lines are present.
+    pub heredoc: Option<Heredoc<'a>>,
+}
+
+impl<'a> Redirect<'a> {
+    /// Returns the file descriptor this redirection applies to.
+    #[inline]
+    pub const fn fd(&self) -> u32 {
+        match self.fd {
+            Some(fd) => fd,
+            None => self.operator.default_fd(),
+        }
+    }
+
+    /// Is this redirection a here-document missing its body, or the line ending it?
+    #[inline]
+    pub const fn needs_heredoc(&self) -> bool {
+        if !self.operator.is_heredoc() {
+            return false;
+        }
+
+        match &self.heredoc {
+            Some(heredoc) => !heredoc.terminated,
+            None => true,
+        }
+    }
+
+    /// Is this redirection missing a target?
+    #[inline]
+    pub fn is_incomplete(&self) -> bool {
+        match &self.target {
+            Some(target) => target.is_incomplete(),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use crate::parser::Parser;
+use crate::{Case, CommandError, For, Function, If, List, Operator, Piped, While};
+
+/// A sequence of statements, separated by newlines or `;`.
+#[derive(Clone, Debug)]
+pub struct Script<'a> {
+    pub statements: Vec<Statement<'a>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Statement<'a> {
+    List(List<'a>),
+    If(If<'a>),
+    While(While<'a>),
+    For(For<'a>),
+    Case(Case<'a>),
+    Function(Function<'a>),
+    Pipeline(Piped<'a>),
+    /// A statement following `&&` or `||`, run depending on the status of those before it.
+    ///
+    /// A list continues from the operator, so only its first pipeline depends on it.
+    AndOr(Operator, Box<Statement<'a>>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ScriptError<'a> {
+    Command(CommandError<'a>),
+    /// The script ended before this word, i.e. `fi`, a closing quote, the delimiter of a
+    /// here-document, or a command following `|`.
+    Incomplete(&'a str),
+    /// A word or operator which isn't allowed where it is, i.e. `done` outside of a loop.
+    Unexpected(&'a str),
+}
+
+impl<'a> Script<'a> {
+    #[inline]
+    pub fn try_parse(string: &'a str) -> Result<Self, ScriptError<'a>> {
+        Parser::new(string).parse()
+    }
+
+    /// Is this script empty, i.e. blank lines?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+}
+
+impl<'a> ScriptError<'a> {
+    /// Did the script end early, so more lines may complete it?
+    #[inline]
+    pub const fn is_incomplete(&self) -> bool {
+        matches!(self, ScriptError::Incomplete(_))
+    }
+
+    /// Returns the line of `string`, the script which failed to parse, that the error is on,
+    /// counting from 1.
+    ///
+    /// A script which ended early is in error at its last line.
+    #[inline]
+    pub fn line(&self, string: &'a str) -> usize {
+        let found = match self {
+            ScriptError::Command(
+                CommandError::IncompleteArg(_, found) | CommandError::IncompleteVar(_, found),
+            )
+            | ScriptError::Unexpected(found) => Some(*found),
+            ScriptError::Incomplete(_expected) => None,
+        };
+
+        // errors borrow from the script, so their offset is that of the slice
+        let offset = found
+            .and_then(|found| (found.as_ptr() as usize).checked_sub(string.as_ptr() as usize))
+            .filter(|offset| *offset <= string.len())
+            .unwrap_or_else(|| string.trim_end().len());
+
+        // SAFETY: `offset` is within `string`, and on a character boundary, as `found` is.
+        let before = unsafe { string.get_unchecked(..offset) };
+
+        before.matches('\n').count() + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Element;
+
+    #[test]
+    fn unterminated_quote_is_incomplete() {
+        for script in ["echo \"abc", "echo 'abc", "echo `abc", "echo a\"b\nc"] {
+            let error = Script::try_parse(script).unwrap_err();
+
+            assert!(error.is_incomplete(), "{script:?}: {error:?}");
+        }
+    }
+
+    #[test]
+    fn quote_completes_word() {
+        assert!(Script::try_parse("y\"").unwrap_err().is_incomplete());
+        assert!(Script::try_parse("a\"b\"").is_ok());
+    }
+
+    #[test]
+    fn quoted_strings_join_words() {
+        let values: Vec<_> =
+            crate::Args::new("printf --opt=\"a b\" \"$HOME\"/x 'a''b' foo\"bar\"baz|x")
+                .filter_map(|arg| arg.value())
+                .map(|value| value.as_str())
+                .collect();
+
+        assert_eq!(
+            values,
+            [
+                "printf",
+                "--opt=\"a b\"",
+                "\"$HOME\"/x",
+                "'a''b'",
+                "foo\"bar\"baz",
+                "x"
+            ]
+        );
+        assert!(Script::try_parse("echo foo\"bar")
+            .unwrap_err()
+            .is_incomplete());
+    }
+
+    #[test]
+    fn trailing_backslash_is_incomplete() {
+        let error = Script::try_parse("echo a \\").unwrap_err();
+
+        assert!(error.is_incomplete(), "{error:?}");
+        assert!(Script::try_parse("echo a\\\\").is_ok());
+    }
+
+    #[test]
+    fn dangling_operator_is_incomplete() {
+        for script in ["echo a |", "echo a &&", "echo a || ", "echo a | # comment"] {
+            let error = Script::try_parse(script).unwrap_err();
+
+            assert!(error.is_incomplete(), "{script:?}: {error:?}");
+        }
+    }
+
+    #[test]
+    fn dangling_operator_continues_list() {
+        let script = Script::try_parse(
+            "echo a |
+cat &&
+
+echo b
+echo c",
+        )
+        .unwrap();
+
+        assert_eq!(script.statements.len(), 2);
+    }
+
+    #[test]
+    fn compound_after_and_or() {
+        let script = Script::try_parse("a && if b; then c; fi || d && e\nf").unwrap();
+
+        assert!(matches!(
+            script.statements.as_slice(),
+            [
+                Statement::List(_),
+                Statement::AndOr(Operator::And, if_clause),
+                Statement::AndOr(Operator::Or, list),
+                Statement::List(_),
+            ] if matches!(**if_clause, Statement::If(_)) && matches!(**list, Statement::List(_))
+        ));
+
+        assert!(Script::try_parse("while a; do b; done &&")
+            .unwrap_err()
+            .is_incomplete());
+    }
+
+    #[test]
+    fn compound_in_pipeline() {
+        let script = Script::try_parse("for x in a b; do echo $x; done | wc -l").unwrap();
+
+        assert!(matches!(
+            script.statements.as_slice(),
+            [Statement::Pipeline(piped)] if matches!(
+                piped.elements.as_slice(),
+                [Element::Compound(for_clause, _), Element::Command(command)]
+                    if matches!(**for_clause, Statement::For(_))
+                        && command.program.as_str() == "wc"
+            ) && piped.string == "for x in a b; do echo $x; done | wc -l"
+        ));
+
+        let script = Script::try_parse("a; b | while c; do d; done && e").unwrap();
+
+        assert!(matches!(
+            script.statements.as_slice(),
+            [
+                Statement::List(_),
+                Statement::Pipeline(piped),
+                Statement::AndOr(Operator::And, _),
+            ] if piped.elements.len() == 2
+        ));
+
+        assert!(Script::try_parse("for x in a; do b; done |")
+            .unwrap_err()
+            .is_incomplete());
+    }
+
+    #[test]
+    fn redirected_compound() {
+        let script = Script::try_parse("if a; then b; fi 2> f").unwrap();
+
+        assert!(matches!(
+            script.statements.as_slice(),
+            [Statement::Pipeline(piped)] if matches!(
+                piped.elements.as_slice(),
+                [Element::Compound(_, redirects)] if redirects.len() == 1
+                    && redirects[0].fd() == 2
+            )
+        ));
+
+        assert!(Script::try_parse("if a; then b; fi c").is_err());
+    }
+
+    #[test]
+    fn function_brace_after_parens() {
+        for script in ["f(){ echo hi; }", "f (){ echo hi; }", "f() { echo hi; }"] {
+            let script = Script::try_parse(script).unwrap();
+
+            assert!(matches!(
+                script.statements.as_slice(),
+                [Statement::Function(function)]
+                    if function.name == "f" && function.source.trim() == "echo hi;"
+            ));
+        }
+    }
+
+    #[test]
+    fn line_continuation_joins_lines() {
+        let script = Script::try_parse("echo a \\\nb\necho c").unwrap();
+
+        assert_eq!(script.statements.len(), 2);
+    }
+}
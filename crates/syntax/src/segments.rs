@@ -0,0 +1,48 @@
+use crate::{Common, Quote, Value};
+
+/// Split a word into the quoted strings, and unquoted text, joined within it, i.e. `--opt="a b"`
+/// into `--opt=` and `"a b"`.
+#[derive(Clone, Debug)]
+pub struct Segments<'a> {
+    iter: Common<'a>,
+    /// A value yielded whole, before anything else.
+    single: Option<Value<'a>>,
+}
+
+impl<'a> Segments<'a> {
+    #[inline]
+    pub fn new(word: &'a str) -> Self {
+        let iter = Common::new(word);
+
+        Self { iter, single: None }
+    }
+
+    /// Yield only `value`, a quoted string.
+    #[inline]
+    pub(crate) fn single(value: Value<'a>) -> Self {
+        Self {
+            iter: Common::new(""),
+            single: Some(value),
+        }
+    }
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Value<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Value<'a>> {
+        if let Some(value) = self.single.take() {
+            return Some(value);
+        }
+
+        let character = self.iter.next()?;
+        let segment = match Quote::from_char(character) {
+            // SAFETY: a quoted string is always a value.
+            Some(quote) => unsafe { self.iter.next_string(quote).value_unchecked() },
+            None => Value::Word(self.iter.next_text(false)),
+        };
+
+        Some(segment)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{Arg, Args, Operator};
+
+/// Split a string by operators, yielding each part along with the operator following it, and the
+/// operator's surrounding whitespace.
+///
+/// Parts never contain the bodies of here-documents, they end where the body begins.
+#[derive(Clone, Debug)]
+pub(crate) struct Split<'a> {
+    iter: Args<'a>,
+    predicate: fn(&Operator) -> bool,
+    start: usize,
+    /// The start of a here-document body within the current part.
+    end: Option<usize>,
+    string: &'a str,
+    done: bool,
+}
+
+impl<'a> Split<'a> {
+    #[inline]
+    pub fn new(string: &'a str, predicate: fn(&Operator) -> bool) -> Self {
+        let iter = Args::new(string);
+
+        Self {
+            iter,
+            predicate,
+            start: 0,
+            end: None,
+            string,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Split<'a> {
+    type Item = (&'a str, Option<(Operator, &'a str)>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            let offset = self.iter.offset();
+
+            match self.iter.next() {
+                Some(Arg::Operator(operator)) if (self.predicate)(&operator) => {
+                    let offset = self.end.take().unwrap_or(offset);
+                    // SAFETY: `start` and `offset` are always on a character boundary.
+                    let part = unsafe { self.string.get_unchecked(self.start..offset) };
+                    let part = part.trim_end();
+                    let start = self.start + part.len();
+
+                    // the operator owns the whitespace following it
+                    loop {
+                        let mut iter = self.iter.clone();
+
+                        match iter.next() {
+                            Some(Arg::Whitespace(_)) => self.iter = iter,
+                            _ => break,
+                        }
+                    }
+
+                    let end = self.iter.offset();
+                    // SAFETY: `start` and `end` are always on a character boundary.
+                    let operator_str = unsafe { self.string.get_unchecked(start..end) };
+
+                    self.start = end;
+
+                    return Some((part, Some((operator, operator_str))));
+                }
+                Some(Arg::Heredoc(_heredoc)) => {
+                    self.end.get_or_insert(offset);
+                }
+                Some(_arg) => {}
+                None => {
+                    self.done = true;
+
+                    let end = self.end.unwrap_or(self.string.len());
+                    // SAFETY: `start` and `end` are always on a character boundary.
+                    let part = unsafe { self.string.get_unchecked(self.start..end) };
+
+                    return Some((part, None));
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,9 @@
-use crate::Value;
+use crate::{Operator, Value};
 use core::hint;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Token<'a> {
+    Operator(Operator),
     Value(Value<'a>),
     Whitespace(&'a str),
 }
@@ -12,11 +13,18 @@ impl<'a> Token<'a> {
     #[inline]
     pub const fn as_str(&self) -> &'a str {
         match self {
+            Token::Operator(operator) => operator.as_str(),
             Token::Value(arg) => arg.as_str(),
             Token::Whitespace(string) => string,
         }
     }
 
+    /// Is this token an operator?
+    #[inline]
+    pub const fn is_operator(&self) -> bool {
+        matches!(self, Token::Operator(_))
+    }
+
     /// Is this token a value?
     #[inline]
     pub const fn is_value(&self) -> bool {
@@ -1,10 +1,12 @@
-use crate::Quote;
+use crate::{braces, escape, List, Parts, Quote, RangeTooLong, Segments};
 use core::hint;
+use std::borrow::Cow;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Value<'a> {
     Quoted(Quote, &'a str),
     IncompleteQuoted(Quote, &'a str),
+    /// Unquoted text, perhaps joined with quoted strings, i.e. `--opt="a b"`.
     Word(&'a str),
 }
 
@@ -19,6 +21,59 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// Split the value into literal text and expansions.
+    ///
+    /// Nothing within single quotes is expanded.
+    #[inline]
+    pub fn parts(&self) -> Parts<'a> {
+        match self.quote() {
+            Some(quote) => Parts::quoted(quote, self.as_str()),
+            None => Parts::new(self.as_str()),
+        }
+    }
+
+    /// Returns the text of the value with its escapes removed, and ANSI-C quoting, `$'...'`,
+    /// expanded, but nothing else.
+    #[inline]
+    pub fn unescape(&self) -> String {
+        self.segments()
+            .map(|segment| escape::unescape(segment.as_str(), segment.quote(), false))
+            .collect()
+    }
+
+    /// Split the value into the quoted strings, and unquoted text, it joins.
+    ///
+    /// A quoted string is its only segment.
+    #[inline]
+    pub fn segments(&self) -> Segments<'a> {
+        match self {
+            Value::Word(word) => Segments::new(word),
+            _ => Segments::single(*self),
+        }
+    }
+
+    /// Expand the braces of a word, `{a,b}` and `{1..10}`, into many words.
+    ///
+    /// Quoted strings are never expanded. Fails if a range is too long.
+    #[inline]
+    pub fn braces(&self) -> Result<Vec<Cow<'a, str>>, RangeTooLong> {
+        match self {
+            Value::Word(string) => braces::expand(string),
+            _ => Ok(vec![Cow::Borrowed(self.as_str())]),
+        }
+    }
+
+    /// Parse the command within backticks, `` `...` ``.
+    ///
+    /// Returns `None` if this value isn't within backticks.
+    #[inline]
+    pub fn command(&self) -> Option<List<'a>> {
+        match self.quote() {
+            Some(Quote::Backtick) => List::try_parse(self.as_str()).ok(),
+            _ => None,
+        }
+    }
+
     /// Returns the quote of the string argument, if present.
     #[inline]
     pub const fn quote(&self) -> Option<Quote> {
@@ -51,10 +106,24 @@ impl<'a> Value<'a> {
         matches!(self, Value::Quoted(_, _) | Value::IncompleteQuoted(_, _))
     }
 
-    // Is this value an incomplete quoted string?
+    /// Is a quote left open at the end of this value?
+    #[inline]
+    pub fn is_incomplete(&self) -> bool {
+        self.open_quote().is_some()
+    }
+
+    /// Returns the quote left open at the end of this value, if any.
     #[inline]
-    pub const fn is_incomplete(&self) -> bool {
-        matches!(self, Value::IncompleteQuoted(_, _))
+    pub fn open_quote(&self) -> Option<Quote> {
+        let last = match self {
+            Value::Word(_word) => self.segments().last()?,
+            value => *value,
+        };
+
+        match last {
+            Value::IncompleteQuoted(quote, _string) => Some(quote),
+            _ => None,
+        }
     }
 
     /// Returns the word of the string argument, withour checking.
@@ -1,4 +1,4 @@
-use crate::{Common, Quote, Token, Value};
+use crate::{Common, Operator, Quote, Token, Value};
 use core::hint;
 
 #[derive(Clone, Debug)]
@@ -48,6 +48,7 @@ impl<'a> Vars<'a> {
 
         while let Some(character) = iter.peek() {
             if Quote::from_char(character).is_some()
+                || Operator::is_start(character)
                 || character == '='
                 || character.is_whitespace()
             {
@@ -79,6 +80,7 @@ impl<'a> Vars<'a> {
                 iter.next();
 
                 let character = iter.peek()?;
+                let start = iter.offset();
 
                 // we match it all so
                 iter.next();
@@ -90,7 +92,7 @@ impl<'a> Vars<'a> {
 
                         self.iter.next_string(quote)
                     }
-                    character if character.is_whitespace() => {
+                    character if character.is_whitespace() || Operator::is_start(character) => {
                         self.error = true;
 
                         return Some(Var::IncompletePair(key));
@@ -98,15 +100,14 @@ impl<'a> Vars<'a> {
                     _word => self.iter.next_word(),
                 };
 
-                token
+                self.iter.next_joined(start, token)
             }
-            character if character.is_whitespace() => {
+            // whitespace, an operator, or a quote joined to the word, i.e. `a"b"`
+            _ => {
                 self.error = true;
 
                 return Some(Var::IncompletePair(key));
             }
-            // SAFETY: this cannot be a word as we just consumed a word!
-            _ => unsafe { hint::unreachable_unchecked() },
         };
 
         // SAFETY: we never reach here without a value.
@@ -133,6 +134,11 @@ impl<'a> Iterator for Vars<'a> {
 
                 return Some(Var::UnexpectedChar(character));
             }
+            character if Operator::is_start(character) => {
+                self.error = true;
+
+                return None;
+            }
             character if character.is_whitespace() => self.iter.next_whitespace(),
             _word => return self.next_pair(),
         };
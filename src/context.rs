@@ -1,25 +1,63 @@
-use crate::history::History;
+use crate::expand;
+use crate::history::{History, Record, Search, Settings};
 use crate::input;
 use crate::input::Input;
+use crate::jobs::{Jobs, Process, State};
+use crate::options::Options;
 use crate::paths::{Exes, Summary};
 use crate::session::Session;
+use crate::variables::{Variable, Variables};
 use elysh_edit::Edit;
-use elysh_syntax::Var;
+use elysh_syntax::{List, Operator, Parts, Pipeline, Programs, Quote, Redirect, Script, Var};
+use regex::Regex;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Write;
-use std::path::{Path, PathBuf};
-use std::{env, io, mem};
+use std::io::{Read, Write as _};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{self, Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+use std::{env, io, mem, thread};
 use tokio::fs::File;
-use tokio::process::Command;
+
+/// Signals the shell ignores, and jobs restore to their default action.
+const JOB_SIGNALS: [libc::c_int; 5] = [
+    libc::SIGINT,
+    libc::SIGQUIT,
+    libc::SIGTSTP,
+    libc::SIGTTIN,
+    libc::SIGTTOU,
+];
 
 pub struct Prompt {
     prompt_char: char,
+    status: ExitStatus,
+    /// Is the line a continuation of the previous, i.e. the body of a here-document?
+    continuation: bool,
 }
 
 impl Prompt {
     #[inline]
-    pub const fn new(prompt_char: char) -> Self {
-        Self { prompt_char }
+    pub fn new(prompt_char: char) -> Self {
+        Self {
+            prompt_char,
+            status: exit_status(0),
+            continuation: false,
+        }
+    }
+
+    /// Set whether the line continues the previous.
+    #[inline]
+    pub fn set_continuation(&mut self, continuation: bool) {
+        self.continuation = continuation;
+    }
+
+    /// Set the status of the last command, shown when it failed.
+    #[inline]
+    pub fn set_status(&mut self, status: ExitStatus) {
+        self.status = status;
     }
 }
 
@@ -27,7 +65,28 @@ impl fmt::Display for Prompt {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.write_char(' ')?;
-        fmt.write_str("\x1b[38;5;1m")?;
+
+        if self.continuation {
+            fmt.write_str("\x1b[38;5;8m")?;
+            fmt.write_char(self.prompt_char)?;
+            fmt.write_str("\x1b[m ")?;
+
+            return Ok(());
+        }
+
+        if self.status.success() {
+            fmt.write_str("\x1b[38;5;1m")?;
+        } else {
+            fmt.write_str("\x1b[38;5;8m")?;
+
+            match self.status.signal() {
+                Some(signal) => fmt.write_str(signal_name(signal))?,
+                None => fmt::Display::fmt(&status_code(self.status), fmt)?,
+            }
+
+            fmt.write_str("\x1b[38;5;3m ")?;
+        }
+
         fmt.write_char(self.prompt_char)?;
         fmt.write_str("\x1b[m")?;
         fmt.write_char(' ')?;
@@ -36,6 +95,228 @@ impl fmt::Display for Prompt {
     }
 }
 
+/// Describe an error briefly, without the `(os error N)` suffix of operating system errors.
+#[inline]
+fn error_message(error: &io::Error) -> String {
+    match error.raw_os_error() {
+        Some(libc::ENOENT) => "no such file or directory".into(),
+        Some(libc::ENOTDIR) => "not a directory".into(),
+        Some(libc::EACCES | libc::EPERM) => "permission denied".into(),
+        Some(_code) => {
+            let error = error.to_string();
+
+            match error.split_once(" (os error") {
+                Some((error, _rest)) => error.to_lowercase(),
+                None => error,
+            }
+        }
+        None if error.kind() == io::ErrorKind::NotFound => "no such file or directory".into(),
+        None => error.to_string(),
+    }
+}
+
+/// Remove `.`, and `..` from a path without consulting the filesystem.
+#[inline]
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            path::Component::CurDir => {}
+            path::Component::ParentDir => {
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+
+    normal
+}
+
+/// Construct an exit status from an exit code.
+#[inline]
+pub fn exit_status(code: i32) -> ExitStatus {
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+/// Returns the exit code of a status, or `128 + signal` if a signal killed the process.
+#[inline]
+pub fn status_code(status: ExitStatus) -> i32 {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 0,
+    }
+}
+
+/// Returns the process ID of the shell, `$$`.
+///
+/// It's read once, so a subshell forked from the shell reports its parent's, as POSIX asks.
+#[inline]
+pub fn shell_pid() -> libc::pid_t {
+    static PID: OnceLock<libc::pid_t> = OnceLock::new();
+
+    // SAFETY: `getpid` takes nothing, and always succeeds.
+    *PID.get_or_init(|| unsafe { libc::getpid() })
+}
+
+/// Returns the name of a signal, i.e. `SIGINT`.
+#[inline]
+pub fn signal_name(signal: i32) -> &'static str {
+    match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGCHLD => "SIGCHLD",
+        libc::SIGCONT => "SIGCONT",
+        libc::SIGSTOP => "SIGSTOP",
+        libc::SIGTSTP => "SIGTSTP",
+        libc::SIGTTIN => "SIGTTIN",
+        libc::SIGTTOU => "SIGTTOU",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        _ => "SIG?",
+    }
+}
+
+/// Have `process` restore the signals the shell ignores to their default action.
+#[inline]
+fn reset_signals(process: &mut Command) {
+    // SAFETY: only async-signal-safe functions are called.
+    unsafe {
+        process.pre_exec(|| {
+            for signal in JOB_SIGNALS {
+                libc::signal(signal, libc::SIG_DFL);
+            }
+
+            Ok(())
+        });
+    }
+}
+
+/// Returns the read end of a pipe which `content` is written to.
+///
+/// The content is written from another thread, so that the pipe never fills up before the
+/// command begins reading it.
+#[inline]
+fn here(content: String) -> io::Result<std::fs::File> {
+    let (reader, mut writer) = pipe()?;
+
+    thread::spawn(move || {
+        // the command may exit without reading it all
+        let _ = writer.write_all(content.as_bytes());
+    });
+
+    Ok(reader)
+}
+
+/// Write output meant for a raw terminal to `writer`, dropping carriage returns.
+#[inline]
+fn write_cooked<W: io::Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    for line in bytes.split(|byte| *byte == b'\r') {
+        writer.write_all(line)?;
+    }
+
+    writer.flush()
+}
+
+/// Create a pipe, returning the read and write ends, both closed on exec.
+#[inline]
+fn pipe() -> io::Result<(std::fs::File, std::fs::File)> {
+    let mut fds = [0; 2];
+
+    // SAFETY: `fds` is a valid pointer to two file descriptors.
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: `pipe2` returned two new file descriptors which nothing else owns.
+    unsafe {
+        Ok((
+            std::fs::File::from_raw_fd(fds[0]),
+            std::fs::File::from_raw_fd(fds[1]),
+        ))
+    }
+}
+
+/// Wait for the child `pid` to exit, returning its status.
+#[inline]
+fn wait_pid(pid: libc::pid_t) -> io::Result<ExitStatus> {
+    let mut raw = 0;
+
+    // SAFETY: `raw` is a valid pointer.
+    while unsafe { libc::waitpid(pid, &mut raw, 0) } == -1 {
+        let error = io::Error::last_os_error();
+
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+
+    Ok(ExitStatus::from_raw(raw))
+}
+
+/// Close each file descriptor above stderr that a subshell inherited, other than those in
+/// `keep`, so it doesn't hold open pipes the shell meant to close, i.e. that of a here-document.
+#[inline]
+fn close_inherited(keep: &[RawFd]) {
+    let fds: Vec<RawFd> = match std::fs::read_dir("/proc/self/fd") {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .collect(),
+        Err(_error) => return,
+    };
+
+    for fd in fds {
+        if fd > libc::STDERR_FILENO && !keep.contains(&fd) {
+            // SAFETY: `close` takes no pointers, and nothing in the subshell uses these.
+            unsafe {
+                libc::close(fd);
+            }
+        }
+    }
+}
+
+/// Open redirections, the files to keep open, and the `(fd, source)` pairs to duplicate, closing
+/// `fd` when there is no source.
+type Redirects = (Vec<std::fs::File>, Vec<(RawFd, Option<RawFd>)>);
+
+/// Duplicate each source onto its file descriptor, in order, or close it when there is none.
+///
+/// Only async-signal-safe functions are called, so this may run between fork and exec.
+#[inline]
+fn dup_all(dups: &[(RawFd, Option<RawFd>)]) -> io::Result<()> {
+    for (fd, source) in dups {
+        // SAFETY: none of these take pointers, and bad descriptors only fail.
+        let result = unsafe {
+            match source {
+                // `dup2` leaves close-on-exec alone when both are the same
+                Some(source) if source == fd => libc::fcntl(*fd, libc::F_SETFD, 0),
+                Some(source) => libc::dup2(*source, *fd),
+                None => libc::close(*fd),
+            }
+        };
+
+        if result == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
 pub mod env2 {
     use std::env;
     use std::path::{Path, PathBuf};
@@ -48,7 +329,8 @@ pub mod env2 {
             .unwrap_or_else(|| PathBuf::from("/"))
     }
 
-    /// Returns the environment variable `XDG_DATA_HOME/elysh` or `{home}/.local/share/elysh`.
+    /// Returns the environment variable `XDG_DATA_HOME/elysh` or `{home}/.local/share/elysh`, when
+    /// it's unset or empty.
     #[inline]
     pub fn data_dir<P>(home: P) -> PathBuf
     where
@@ -56,61 +338,195 @@ pub mod env2 {
     {
         let home = home.as_ref().to_path_buf();
         let data_dir = env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
             .map(PathBuf::from)
             .unwrap_or_else(|| home.join(".local/share"));
 
         data_dir.join("elysh")
     }
+
+    /// Returns the environment variable `XDG_CONFIG_HOME/elysh` or `{home}/.config/elysh`, when
+    /// it's unset or empty.
+    #[inline]
+    pub fn config_dir<P>(home: P) -> PathBuf
+    where
+        P: AsRef<Path>,
+    {
+        let home = home.as_ref().to_path_buf();
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".config"));
+
+        config_dir.join("elysh")
+    }
+}
+
+/// A command of a pipeline, as it is spawned.
+pub enum Stage<'p, 'a> {
+    /// A program, run with its arguments, variables, and redirections.
+    Program(&'p elysh_syntax::Command<'a>),
+    /// The shell, forked to run a function, builtin, or compound command, with redirections.
+    Subshell(&'p [Redirect<'a>]),
 }
 
 pub struct Context {
+    /// Abbreviations, expanded within the line as they are typed.
+    abbreviations: BTreeMap<String, String>,
+    /// Aliases, replacing the program they name before it runs.
+    aliases: BTreeMap<String, String>,
+    /// Lines entered so far of a command which continues onto the next line.
+    continuation: Option<String>,
+    config_dir: PathBuf,
     pub edit: Edit,
     current_dir: PathBuf,
     data_dir: PathBuf,
+    /// Directories saved by `pushd`, the most recent last.
+    dir_stack: Vec<PathBuf>,
     pub exact: char,
     pub execute_edit: bool,
     exes: Exes,
+    /// Variables made local by each running function, along with their previous values.
+    frames: Vec<Vec<(String, Option<Variable>)>>,
+    /// The body of each shell function.
+    functions: BTreeMap<String, String>,
     history: History,
+    /// `HISTIGNORE_REGEX`, and the expression compiled from it, `None` when invalid, so it's
+    /// only compiled again once the variable changes.
+    history_regex: Option<(String, Option<Regex>)>,
     pub home_dir: PathBuf,
+    pub jobs: Jobs,
     last_edit: Option<Edit>,
+    /// How many loops are running, for `break` and `continue`.
+    pub loops: usize,
+    /// The name of the shell, or of the script it runs, `$0`.
+    name: String,
+    pub options: Options,
+    /// Process group of the shell.
+    pgid: libc::pid_t,
+    /// Positional parameters, `$1`, `$2`, and so on.
+    positional: Vec<String>,
     pub prompt: Prompt,
     pub rest: char,
-    pub session: Session,
+    /// The search through history under way, begun by Ctrl-R.
+    search: Option<Search>,
+    /// The terminal, `None` when running a script.
+    session: Option<Session>,
     showkeys: bool,
+    /// How many files are being sourced, one within another.
+    pub sources: usize,
+    /// Status of the last command, `$?`.
+    status: ExitStatus,
+    variables: Variables,
 }
 
 impl Context {
     #[inline]
-    pub async fn new(tty: File) -> io::Result<Self> {
+    pub async fn new(tty: Option<File>) -> io::Result<Self> {
         let current_dir = env::current_dir()?;
         let home_dir = env2::home_dir();
         let data_dir = env2::data_dir(&home_dir);
+        let config_dir = env2::config_dir(&home_dir);
 
+        let abbreviations = BTreeMap::new();
+        let aliases = BTreeMap::new();
+        let continuation = None;
+        let dir_stack = Vec::new();
         let edit = Edit::new();
         let exact = '1';
         let execute_edit = false;
-        let exes = Exes::from_env().await?;
-        let history = History::new(&data_dir).await;
+        // nothing is highlighted without a terminal
+        let exes = match &tty {
+            Some(_tty) => Exes::from_env().await?,
+            None => Exes::new(&BTreeMap::new()),
+        };
+        let frames = Vec::new();
+        let functions = BTreeMap::new();
+        let history = History::new(&data_dir);
+        let jobs = Jobs::new();
         let last_edit = None;
+        let loops = 0;
+        let name = String::from("elysh");
+        let options = Options::new();
+        let positional = Vec::new();
         let prompt = Prompt::new('>');
         let rest = '8';
-        let session = Session::new(tty)?;
+        let search = None;
+        let session = tty.map(Session::new).transpose()?;
         let showkeys = false;
+        let sources = 0;
+        let status = exit_status(0);
+        let mut variables = Variables::from_env();
+
+        let shlvl = variables
+            .get("SHLVL")
+            .and_then(|shlvl| shlvl.parse::<i64>().ok())
+            .unwrap_or(0);
+
+        for (name, value) in [
+            ("PWD", current_dir.display().to_string()),
+            ("SHLVL", (shlvl + 1).to_string()),
+        ] {
+            let _ = variables.set(name, value);
+
+            variables.export(name, true);
+        }
+
+        // lead our own process group, and take the terminal
+        if session.is_some() {
+            for signal in JOB_SIGNALS {
+                // SAFETY: ignoring a signal installs no handler, and each of `JOB_SIGNALS` may be
+                // ignored.
+                unsafe {
+                    libc::signal(signal, libc::SIG_IGN);
+                }
+            }
+
+            // SAFETY: `setpgid` takes no pointers, and `0, 0` only moves the shell itself.
+            unsafe {
+                libc::setpgid(0, 0);
+            }
+        }
+
+        // SAFETY: `getpgrp` has no arguments, and can't fail.
+        let pgid = unsafe { libc::getpgrp() };
+
+        if let Some(session) = &session {
+            let _ = session.set_foreground(pgid);
+        }
 
         Ok(Self {
+            abbreviations,
+            aliases,
+            continuation,
+            config_dir,
             edit,
             current_dir,
             data_dir,
+            dir_stack,
             exact,
             execute_edit,
             exes,
+            frames,
+            functions,
             history,
+            history_regex: None,
             home_dir,
+            jobs,
             last_edit,
+            loops,
+            name,
+            options,
+            pgid,
+            positional,
             prompt,
             rest,
+            search,
             session,
             showkeys,
+            sources,
+            status,
+            variables,
         })
     }
 
@@ -144,17 +560,49 @@ impl Context {
             Ok(rest) => self.home_dir.join(rest),
             Err(_error) => path.to_path_buf(),
         };
-        
+
         path.components().collect()
     }
 
+    /// Returns the path of the file run when an interactive shell starts.
+    #[inline]
+    pub fn config_path(&self) -> PathBuf {
+        self.config_dir.join("config.elysh")
+    }
+
+    /// Returns the path of the file run when a login shell starts.
+    #[inline]
+    pub fn profile_path(&self) -> PathBuf {
+        self.config_dir.join("profile.elysh")
+    }
+
+    /// Is there a terminal, rather than a script being run?
+    #[inline]
+    pub fn is_interactive(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Write to the terminal.
+    ///
+    /// Without one, this is written to stdout instead, without the carriage returns raw mode
+    /// needs.
+    #[inline]
+    pub async fn write_all(&self, bytes: &[u8]) -> io::Result<()> {
+        match &self.session {
+            Some(session) => session.write_all(bytes).await,
+            None => write_cooked(&mut io::stdout(), bytes),
+        }
+    }
+
     /// Enable raw mode for all sorts of fancy terminalisms.
     #[inline]
     pub async fn enable_raw(&self) -> io::Result<()> {
-        self.session.set_raw()?;
-        self.session.set_nonblocking()?;
-        // enable bracketed paste mode
-        self.session.write_all(b"\x1b[?2004h").await?;
+        if let Some(session) = &self.session {
+            session.set_raw()?;
+            session.set_nonblocking()?;
+            // enable bracketed paste mode
+            session.write_all(b"\x1b[?2004h").await?;
+        }
 
         Ok(())
     }
@@ -162,10 +610,12 @@ impl Context {
     /// Disable raw mode to run programs and such.
     #[inline]
     pub async fn disable_raw(&self) -> io::Result<()> {
-        // disable bracketed paste mode
-        self.session.write_all(b"\x1b[?2004l").await?;
-        self.session.set_cooked()?;
-        self.session.set_blocking()?;
+        if let Some(session) = &self.session {
+            // disable bracketed paste mode
+            session.write_all(b"\x1b[?2004l").await?;
+            session.set_cooked()?;
+            session.set_blocking()?;
+        }
 
         Ok(())
     }
@@ -175,6 +625,16 @@ impl Context {
         self.edit.command()
     }
 
+    #[inline]
+    pub fn pipeline(&self) -> Result<Pipeline<'_>, elysh_syntax::CommandError<'_>> {
+        self.edit.pipeline()
+    }
+
+    #[inline]
+    pub fn list(&self) -> Result<List<'_>, elysh_syntax::CommandError<'_>> {
+        self.edit.list()
+    }
+
     #[inline]
     pub fn search_program(&self, program: &str) -> Summary {
         self.exes.search_one(program)
@@ -182,12 +642,14 @@ impl Context {
 
     #[inline]
     pub fn suggest(&self) -> Summary {
-        if self.edit.is_empty() {
+        if self.edit.is_empty() || self.is_continuation() {
             return Summary::NoMatch;
         }
 
-        match self.command() {
-            Ok(command) => {
+        match self.list() {
+            Ok(list) => {
+                let command = list.last().last();
+
                 if command.program.as_str().is_empty() {
                     Summary::NoMatch
                 } else {
@@ -234,7 +696,7 @@ impl Context {
         let showkeys = Showkeys(string, input);
         let edit = format!("{showkeys}");
 
-        self.session.write_all(edit.as_bytes()).await?;
+        self.write_all(edit.as_bytes()).await?;
 
         Ok(())
     }
@@ -242,19 +704,22 @@ impl Context {
     #[inline]
     pub async fn next_input(&self) -> io::Result<Input> {
         let input = loop {
-            let bytes = self.session.wait_for_user().await?;
-            // SAFETY: `bytes` technically shouldn't be fucky?
-            let string = unsafe { std::str::from_utf8_unchecked(&bytes) };
+            let bytes = match &self.session {
+                Some(session) => session.wait_for_user().await?,
+                None => return Err(io::Error::new(io::ErrorKind::Other, "not a terminal")),
+            };
+            // a key may be cut short, so it isn't always valid UTF-8
+            let string = String::from_utf8_lossy(&bytes);
 
             let input = input::map(&bytes);
 
             match input {
                 Some(input) => {
-                    self.showkeys(string, Some(&input)).await?;
+                    self.showkeys(&string, Some(&input)).await?;
 
                     break input;
                 }
-                None => self.showkeys(string, None).await?,
+                None => self.showkeys(&string, None).await?,
             }
         };
 
@@ -263,43 +728,264 @@ impl Context {
 
     #[inline]
     pub fn update_edit_with_history(&mut self) {
-        if let Some(item) = self.history.get() {
+        if let Some(record) = self.history.get() {
             if self.last_edit.is_none() {
-                let item_edit = Edit::from(item.clone());
+                let item_edit = Edit::from(record.text.clone());
 
                 self.last_edit = Some(mem::replace(&mut self.edit, item_edit));
             } else {
-                self.edit = Edit::from(item.clone());
+                self.edit = Edit::from(record.text.clone());
             }
-        } else {
-            self.edit = self.last_edit.take().unwrap_or_default();
+        } else if let Some(last_edit) = self.last_edit.take() {
+            self.edit = last_edit;
         }
 
         self.edit.to_end();
     }
 
+    /// Step to older history, only through items beginning with the line typed, if any.
     #[inline]
     pub fn history_up(&mut self) {
-        self.history.next();
+        if self.history.position() == 0 {
+            let settings = self.history_settings();
+
+            self.history.sync(&settings);
+        }
+
+        match self.history_prefix() {
+            Some(prefix) => self.history.next_prefixed(&prefix),
+            None => self.history.next(),
+        }
+
         self.update_edit_with_history();
     }
 
+    /// Step to newer history, only through items beginning with the line typed, if any.
     #[inline]
     pub fn history_down(&mut self) {
-        self.history.next_back();
+        match self.history_prefix() {
+            Some(_prefix) => self.history.next_back_prefixed(),
+            None => self.history.next_back(),
+        }
+
         self.update_edit_with_history();
     }
 
+    /// Returns the line typed before stepping into history, unless it is empty.
+    #[inline]
+    fn history_prefix(&self) -> Option<String> {
+        let edit = self.last_edit.as_ref().unwrap_or(&self.edit);
+
+        (!edit.is_empty()).then(|| edit.as_str().to_string())
+    }
+
+    /// Returns the search through history under way.
+    #[inline]
+    pub fn search(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    /// Begin searching history, or step to the next older match when already searching.
+    #[inline]
+    pub fn search_older(&mut self) {
+        match &mut self.search {
+            Some(search) => search.older(&self.history),
+            None => {
+                let search = Search::new(self.history.position(), self.edit.clone());
+
+                let settings = self.history_settings();
+
+                self.history.sync(&settings);
+
+                self.search = Some(search);
+            }
+        }
+
+        self.show_search();
+    }
+
+    /// Step to the next newer match.
+    #[inline]
+    pub fn search_newer(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.newer(&self.history);
+        }
+
+        self.show_search();
+    }
+
+    /// Add a character to what is searched for.
+    #[inline]
+    pub fn search_insert(&mut self, character: char) {
+        if let Some(search) = &mut self.search {
+            search.push(&self.history, character);
+        }
+
+        self.show_search();
+    }
+
+    /// Remove the last character of what is searched for.
+    #[inline]
+    pub fn search_remove(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.pop(&self.history);
+        }
+
+        self.show_search();
+    }
+
+    /// Stop searching, keeping the match as the line being edited.
+    #[inline]
+    pub fn accept_search(&mut self) {
+        self.search = None;
+    }
+
+    /// Stop searching, restoring the line being edited before the search began.
+    #[inline]
+    pub fn cancel_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            let (position, edit) = search.original();
+
+            self.restore_edit(position, edit.clone());
+        }
+    }
+
+    /// Edit the item matching, or the original line when nothing matches yet.
+    #[inline]
+    fn show_search(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+
+        match search.found() {
+            Some(index) => {
+                self.history.set_index(index);
+                self.update_edit_with_history();
+            }
+            None => {
+                let (position, edit) = search.original();
+                let edit = edit.clone();
+
+                self.restore_edit(position, edit);
+            }
+        }
+    }
+
+    #[inline]
+    fn restore_edit(&mut self, position: isize, edit: Edit) {
+        self.history.set_position(position);
+
+        // the line before stepping into history is only kept while within it
+        if position == 0 {
+            self.last_edit = None;
+        }
+
+        self.edit = edit;
+    }
+
     #[inline]
     pub fn clear(&mut self) {
         self.edit.clear();
+        self.continuation = None;
+        self.prompt.set_continuation(false);
+    }
+
+    /// Is the line being edited a continuation of the previous?
+    #[inline]
+    pub fn is_continuation(&self) -> bool {
+        self.continuation.is_some()
     }
 
+    /// Take the line being edited, joined onto the lines before it, recording the whole command
+    /// in history once complete.
+    ///
+    /// Returns `None` while a compound command, or a here-document, is missing the line ending
+    /// it, keeping the lines until the next.
+    #[inline]
+    pub fn take_source(&mut self) -> Option<String> {
+        let line: String = mem::take(&mut self.edit).into();
+
+        self.history.reset();
+        self.last_edit = None;
+
+        let source = match self.continuation.take() {
+            Some(mut source) => {
+                source.push('\n');
+                source.push_str(&line);
+                source
+            }
+            None => line,
+        };
+
+        let (incomplete, invalid) = match Script::try_parse(&source) {
+            Ok(_script) => (false, false),
+            Err(error) => (error.is_incomplete(), !error.is_incomplete()),
+        };
+
+        self.prompt.set_continuation(incomplete);
+
+        if incomplete {
+            self.continuation = Some(source);
+
+            return None;
+        }
+
+        let settings = self.history_settings();
+
+        self.history
+            .push(source.clone(), &self.current_dir, &settings);
+
+        // it never runs, so it finishes now, with the status of a syntax error
+        if invalid {
+            self.history.finish(2, &settings);
+        }
+
+        Some(source)
+    }
+
+    /// Returns what history records, and how much it keeps.
+    #[inline]
+    fn history_settings(&mut self) -> Settings {
+        let regex = match self.var("HISTIGNORE_REGEX") {
+            Some(pattern) => match &self.history_regex {
+                Some((compiled, regex)) if *compiled == pattern => regex.clone(),
+                _ => {
+                    // an invalid expression ignores nothing
+                    let regex = Regex::new(&pattern).ok();
+
+                    self.history_regex = Some((pattern, regex.clone()));
+
+                    regex
+                }
+            },
+            None => None,
+        };
+
+        Settings::from_vars(|name| self.var(name), regex, self.options.sharehistory)
+    }
+
+    /// Iterate each record of history, the oldest first.
+    #[inline]
+    pub fn history(&self) -> impl Iterator<Item = &Record> {
+        self.history.iter()
+    }
+
+    /// Remove each record of history `matches`, returning how many.
+    #[inline]
+    pub fn delete_history<F>(&mut self, matches: F) -> io::Result<usize>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.last_edit = None;
+        self.history.delete(matches)
+    }
+
+    /// Record the status of the command last taken, and how long it ran, in history.
     #[inline]
-    pub fn clear_and_record(&mut self) {
-        let edit = mem::take(&mut self.edit);
+    pub fn finish_history(&mut self) {
+        let settings = self.history_settings();
 
-        self.history.push(edit.into());
+        self.history.finish(status_code(self.status), &settings);
     }
 
     #[inline]
@@ -362,47 +1048,1210 @@ impl Context {
         self.showkeys = !self.showkeys;
     }
 
+    /// Returns the status of the last command.
     #[inline]
-    pub async fn save_history(&self) {
-        let _ = self.history.save(&self.data_dir).await;
+    pub fn status(&self) -> ExitStatus {
+        self.status
     }
 
+    /// Record the status of the last command.
     #[inline]
-    pub fn change_dir<P>(&mut self, target_dir: P) -> io::Result<()>
-    where
-        P: AsRef<Path>,
-    {
-        let target_dir = self.expand_path(target_dir);
-        let result = std::env::set_current_dir(&target_dir);
+    pub fn set_status(&mut self, status: ExitStatus) {
+        self.status = status;
+        self.prompt.set_status(status);
+    }
 
-        result
-            .and_then(|_| std::env::current_dir())
-            .map(|target_dir| self.current_dir = target_dir)
+    /// Returns the value of a shell variable, or special parameter.
+    #[inline]
+    pub fn var(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(status_code(self.status).to_string()),
+            "$" => Some(shell_pid().to_string()),
+            "0" => Some(self.name.clone()),
+            "#" => Some(self.positional.len().to_string()),
+            "@" | "*" => Some(self.positional.join(" ")),
+            name => match name.parse::<usize>() {
+                Ok(index) => self.positional.get(index.checked_sub(1)?).cloned(),
+                Err(_error) => self.variables.get(name),
+            },
+        }
     }
 
+    /// Set `$0`, and the positional parameters, i.e. for a script and its arguments.
     #[inline]
-    pub async fn spawn(&self, command: &elysh_syntax::Command<'_>) -> io::Result<io::Result<()>> {
-        self.disable_raw().await?;
+    pub fn set_args(&mut self, name: String, positional: Vec<String>) {
+        self.name = name;
+        self.positional = positional;
+    }
 
-        let result = Command::new(command.program.as_str())
-            .args(command.args.iter().map(|arg| arg.as_str()))
-            .envs(command.vars.iter().flat_map(|var| match var {
-                Var::Pair(key, val) => Some((key, val.as_str())),
-                _ => None,
-            }))
-            .spawn();
-
-        let result = match result {
-            Ok(mut child) => match child.wait().await {
-                Ok(_status) => Ok(Ok(())),
-                Err(error) => Ok(Err(error)),
-            },
-            Err(error) => Err(error),
-        };
+    /// Replace the positional parameters, returning the previous ones.
+    #[inline]
+    pub fn replace_positional(&mut self, positional: Vec<String>) -> Vec<String> {
+        mem::replace(&mut self.positional, positional)
+    }
 
-        self.enable_raw().await?;
+    /// Find a file to source, searching `PATH` when `name` has no slash, then the current
+    /// directory.
+    #[inline]
+    pub fn find_source(&self, name: &str) -> Option<PathBuf> {
+        if name.contains('/') {
+            return Some(self.expand_path(name));
+        }
+
+        let path = self.var("PATH").unwrap_or_default();
+
+        env::split_paths(&path)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .or_else(|| Some(self.current_dir.join(name)).filter(|path| path.is_file()))
+    }
+
+    /// Returns the positional parameters, `$@`.
+    #[inline]
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    /// Define an alias, replacing the program `name` with `value`.
+    #[inline]
+    pub fn define_alias(&mut self, name: &str, value: &str) {
+        self.exes.insert(name);
+        self.aliases.insert(name.into(), value.into());
+    }
+
+    /// Remove an alias, returning whether it existed.
+    #[inline]
+    pub fn remove_alias(&mut self, name: &str) -> bool {
+        let removed = self.aliases.remove(name).is_some();
 
-        result
+        self.forget_name(name);
+
+        removed
+    }
+
+    /// Iterate each alias, and its value.
+    #[inline]
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the value of the alias `name`.
+    #[inline]
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    /// Replace each program of `source` which is an alias with its value.
+    ///
+    /// Values are expanded in turn, except for aliases already being expanded, so
+    /// `alias ls='ls -F'` doesn't expand forever.
+    #[inline]
+    pub fn expand_aliases(&self, source: &str) -> String {
+        self.expand_aliases_within(source, &mut Vec::new())
+    }
+
+    #[inline]
+    fn expand_aliases_within<'a>(&'a self, source: &str, expanding: &mut Vec<&'a str>) -> String {
+        let mut expanded = String::with_capacity(source.len());
+        let mut last = 0;
+
+        for (start, program) in Programs::new(source) {
+            let (name, value) = match self.aliases.get_key_value(program) {
+                Some(alias) => alias,
+                None => continue,
+            };
+
+            if expanding.contains(&name.as_str()) {
+                continue;
+            }
+
+            expanded.push_str(&source[last..start]);
+            expanding.push(name);
+            expanded.push_str(&self.expand_aliases_within(value, expanding));
+            expanding.pop();
+            last = start + program.len();
+        }
+
+        expanded.push_str(&source[last..]);
+        expanded
+    }
+
+    /// Define an abbreviation, expanding `name` to `expansion` when typed as a program.
+    #[inline]
+    pub fn define_abbreviation(&mut self, name: &str, expansion: &str) {
+        self.exes.insert(name);
+        self.abbreviations.insert(name.into(), expansion.into());
+    }
+
+    /// Remove an abbreviation, returning whether it existed.
+    #[inline]
+    pub fn remove_abbreviation(&mut self, name: &str) -> bool {
+        let removed = self.abbreviations.remove(name).is_some();
+
+        self.forget_name(name);
+
+        removed
+    }
+
+    /// Iterate each abbreviation, and its expansion.
+    #[inline]
+    pub fn abbreviations(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.abbreviations
+            .iter()
+            .map(|(name, expansion)| (name.as_str(), expansion.as_str()))
+    }
+
+    /// Expand the program just before the cursor, when it is an abbreviation.
+    ///
+    /// Done when space, or enter is pressed, so history records the expansion.
+    #[inline]
+    pub fn expand_abbreviation(&mut self) {
+        if self.is_continuation() {
+            return;
+        }
+
+        let start = self.edit.start();
+        let (name, expansion) = match Programs::new(start)
+            .last()
+            .filter(|(offset, program)| offset + program.len() == start.len())
+            .and_then(|(_offset, program)| self.abbreviations.get_key_value(program))
+        {
+            Some((name, expansion)) => (name.chars().count(), expansion.clone()),
+            None => return,
+        };
+
+        for _ in 0..name {
+            self.edit.remove();
+        }
+
+        self.edit.insert_str(&expansion);
+    }
+
+    /// Forget a name which was highlighted as a program, unless something else still defines it.
+    #[inline]
+    fn forget_name(&mut self, name: &str) {
+        if !(self.aliases.contains_key(name)
+            || self.abbreviations.contains_key(name)
+            || self.functions.contains_key(name))
+        {
+            self.exes.remove(name);
+        }
+    }
+
+    /// Define a shell function, which may then be run by name.
+    #[inline]
+    pub fn define_function(&mut self, name: &str, body: &str) {
+        self.exes.insert(name);
+        self.functions.insert(name.into(), body.into());
+    }
+
+    /// Remove a shell function.
+    #[inline]
+    pub fn remove_function(&mut self, name: &str) {
+        self.functions.remove(name);
+        self.forget_name(name);
+    }
+
+    /// Returns the body of the shell function `name`.
+    #[inline]
+    pub fn function(&self, name: &str) -> Option<String> {
+        self.functions.get(name).cloned()
+    }
+
+    /// Is a shell function running?
+    #[inline]
+    pub fn in_function(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    /// Begin running a function, with `args` as the positional parameters.
+    ///
+    /// Returns the previous positional parameters, to be given back to `leave_function`.
+    #[inline]
+    pub fn enter_function(&mut self, args: Vec<String>) -> Vec<String> {
+        self.frames.push(Vec::new());
+
+        mem::replace(&mut self.positional, args)
+    }
+
+    /// Finish running a function, restoring the variables it made local, and the positional
+    /// parameters.
+    #[inline]
+    pub fn leave_function(&mut self, positional: Vec<String>) {
+        for (name, variable) in self.frames.pop().into_iter().flatten().rev() {
+            // a local made read-only stays that way
+            let _ = self.variables.replace(name, variable);
+        }
+
+        self.positional = positional;
+    }
+
+    /// Make a variable local to the running function, setting it, or unsetting it when `value`
+    /// is `None`.
+    #[inline]
+    pub fn set_local(&mut self, name: &str, value: Option<String>) -> io::Result<()> {
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "only meaningful in a function",
+                ))
+            }
+        };
+
+        let variable = Variable {
+            value,
+            ..Variable::default()
+        };
+
+        let previous = self.variables.replace(name, Some(variable))?;
+
+        // only the value from before the function ran is restored
+        if !frame.iter().any(|(local, _value)| local == name) {
+            frame.push((name.into(), previous));
+        }
+
+        Ok(())
+    }
+
+    /// Set the value of a shell variable.
+    ///
+    /// Fails if the variable is read-only.
+    #[inline]
+    pub fn set_var<V>(&mut self, name: &str, value: V) -> io::Result<()>
+    where
+        V: Into<String>,
+    {
+        self.variables.set(name, value)
+    }
+
+    /// Remove a shell variable.
+    ///
+    /// Fails if the variable is read-only.
+    #[inline]
+    pub fn unset_var(&mut self, name: &str) -> io::Result<()> {
+        self.variables.unset(name)
+    }
+
+    /// Export, or stop exporting a variable to programs.
+    #[inline]
+    pub fn export_var(&mut self, name: &str, exported: bool) {
+        self.variables.export(name, exported);
+    }
+
+    /// Make a variable read-only.
+    #[inline]
+    pub fn make_readonly(&mut self, name: &str) {
+        self.variables.make_readonly(name);
+    }
+
+    /// Iterate each shell variable, by name.
+    #[inline]
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Variable)> {
+        self.variables.iter()
+    }
+
+    /// Change the current directory, keeping `PWD`, and `OLDPWD` up to date.
+    ///
+    /// When `physical` is set symbolic links are resolved, as `cd -P`, otherwise `..` removes
+    /// the last component of the path as it was entered, as `cd -L`.
+    #[inline]
+    pub fn change_dir<P>(&mut self, target_dir: P, physical: bool) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let target_dir = self.expand_path(target_dir);
+        let target_dir = if physical {
+            target_dir
+        } else {
+            normalize_path(&self.current_dir.join(target_dir))
+        };
+
+        std::env::set_current_dir(&target_dir)?;
+
+        self.current_dir = if physical {
+            std::env::current_dir()?
+        } else {
+            target_dir
+        };
+
+        let previous = self.var("PWD").unwrap_or_default();
+        let current = self.current_dir.display().to_string();
+
+        // either being read-only doesn't stop the directory changing
+        let _ = self.variables.set("OLDPWD", previous);
+        let _ = self.variables.set("PWD", current);
+
+        self.variables.export("OLDPWD", true);
+        self.variables.export("PWD", true);
+
+        Ok(())
+    }
+
+    /// Find a directory to change to within `CDPATH`.
+    ///
+    /// Only relative paths not beginning with `.`, or `..` are searched for, and an empty
+    /// entry means the current directory.
+    #[inline]
+    pub fn find_dir(&self, name: &Path) -> Option<PathBuf> {
+        let searched = matches!(name.components().next(), Some(path::Component::Normal(_)));
+
+        if !searched || name.starts_with("~") {
+            return None;
+        }
+
+        let cdpath = self.var("CDPATH")?;
+
+        env::split_paths(&cdpath)
+            .map(|dir| self.expand_path(dir).join(name))
+            .find(|path| self.current_dir.join(path).is_dir())
+    }
+
+    /// Change to `target_dir`, saving the current directory onto the directory stack.
+    #[inline]
+    pub fn push_dir<P>(&mut self, target_dir: P, physical: bool) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let previous = self.current_dir.clone();
+
+        self.change_dir(target_dir, physical)?;
+        self.dir_stack.push(previous);
+
+        Ok(())
+    }
+
+    /// Exchange the current directory with the top of the directory stack.
+    #[inline]
+    pub fn swap_dir(&mut self, physical: bool) -> io::Result<()> {
+        let Some(target_dir) = self.dir_stack.pop() else {
+            return Err(io::Error::new(io::ErrorKind::Other, "no other directory"));
+        };
+
+        match self.push_dir(&target_dir, physical) {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                self.dir_stack.push(target_dir);
+
+                Err(error)
+            }
+        }
+    }
+
+    /// Change to the directory on top of the directory stack, removing it.
+    #[inline]
+    pub fn pop_dir(&mut self, physical: bool) -> io::Result<()> {
+        let Some(target_dir) = self.dir_stack.pop() else {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "directory stack empty",
+            ));
+        };
+
+        match self.change_dir(&target_dir, physical) {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                self.dir_stack.push(target_dir);
+
+                Err(error)
+            }
+        }
+    }
+
+    /// Iterate the directory stack, beginning with the current directory, then the most
+    /// recently saved.
+    #[inline]
+    pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.current_dir.as_path())
+            .chain(self.dir_stack.iter().rev().map(PathBuf::as_path))
+    }
+
+    /// Empty the directory stack.
+    #[inline]
+    pub fn clear_dirs(&mut self) {
+        self.dir_stack.clear();
+    }
+
+    /// Build a process from a command, expanding the program, arguments and variables.
+    ///
+    /// Returns the source which failed to expand along with the error.
+    #[inline]
+    fn process(
+        &mut self,
+        command: &elysh_syntax::Command<'_>,
+    ) -> Result<Command, (String, io::Error)> {
+        let mut argv = Vec::with_capacity(command.args.len() + 1);
+        let mut vars = Vec::with_capacity(command.vars.len());
+
+        for arg in Some(&command.program).into_iter().chain(&command.args) {
+            match expand::fields(self, arg) {
+                Ok(fields) => argv.extend(fields),
+                Err(error) => return Err((arg.as_str().into(), error)),
+            }
+        }
+
+        for var in &command.vars {
+            if let Var::Pair(key, val) = var {
+                match expand::value(self, val) {
+                    Ok(val) => vars.push((key, val)),
+                    Err(error) => return Err((val.as_str().into(), error)),
+                }
+            }
+        }
+
+        // the program may have expanded to nothing
+        let mut argv = argv.into_iter();
+        let mut process = Command::new(argv.next().unwrap_or_default());
+
+        // programs see exactly the exported variables, and those given to the command
+        process
+            .args(argv)
+            .env_clear()
+            .envs(self.variables.exported())
+            .envs(vars);
+
+        Ok(process)
+    }
+
+    /// Report an error relating to `target` to the user.
+    #[inline]
+    pub async fn report(&self, target: &str, error: &io::Error) -> io::Result<()> {
+        let error = error_message(error);
+        let edit = format!("\relysh: `{target}` {error}\r\n");
+
+        match &self.session {
+            Some(session) => session.write_all(edit.as_bytes()).await,
+            None => write_cooked(&mut io::stderr(), edit.as_bytes()),
+        }
+    }
+
+    /// Report an error relating to `target`, at `line` of the script `origin`.
+    #[inline]
+    pub async fn report_at(
+        &self,
+        origin: &str,
+        line: usize,
+        target: &str,
+        error: &io::Error,
+    ) -> io::Result<()> {
+        let target = format!("{origin}:{line}: `{target}`");
+        let error = error_message(error);
+        let edit = format!("\relysh: {target} {error}\r\n");
+
+        match &self.session {
+            Some(session) => session.write_all(edit.as_bytes()).await,
+            None => write_cooked(&mut io::stderr(), edit.as_bytes()),
+        }
+    }
+
+    /// Open the target of each redirection, and have `process` duplicate them onto the
+    /// requested file descriptors before it executes.
+    ///
+    /// The returned files must be kept open until `process` has been spawned. On failure, the
+    /// target which failed is returned along with the error.
+    #[inline]
+    fn redirect(
+        &mut self,
+        process: &mut Command,
+        redirects: &[Redirect<'_>],
+    ) -> Result<Vec<std::fs::File>, (String, io::Error)> {
+        let (files, dups) = self.open_redirects(redirects)?;
+
+        if !dups.is_empty() {
+            // SAFETY: only async-signal-safe functions are called.
+            unsafe {
+                process.pre_exec(move || dup_all(&dups));
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Open the target of each redirection, returning the files, which must be kept open until
+    /// they are duplicated, and `(fd, source)` pairs to apply in order with `dup_all`.
+    ///
+    /// On failure, the target which failed is returned along with the error.
+    #[inline]
+    fn open_redirects(
+        &mut self,
+        redirects: &[Redirect<'_>],
+    ) -> Result<Redirects, (String, io::Error)> {
+        let mut files = Vec::new();
+        // (fd, source) pairs, applied in order, closing fd when there is no source
+        let mut dups: Vec<(RawFd, Option<RawFd>)> = Vec::new();
+
+        for redirect in redirects {
+            let operator = redirect.operator;
+            let fd = redirect.fd() as RawFd;
+
+            if operator.is_heredoc() || operator == Operator::TripleLess {
+                let file = match self.here_document(redirect).and_then(here) {
+                    Ok(file) => file,
+                    Err(error) => return Err((operator.as_str().into(), error)),
+                };
+
+                dups.push((fd, Some(file.as_raw_fd())));
+                files.push(file);
+
+                continue;
+            }
+
+            let target = match &redirect.target {
+                Some(target) => match expand::value(self, target) {
+                    Ok(target) => target,
+                    Err(error) => return Err((target.as_str().into(), error)),
+                },
+                None => {
+                    let error = io::Error::new(io::ErrorKind::InvalidInput, "missing target");
+
+                    return Err((operator.as_str().into(), error));
+                }
+            };
+
+            if operator.is_dup() {
+                if target == "-" {
+                    dups.push((fd, None));
+
+                    continue;
+                }
+
+                if let Ok(source) = target.parse() {
+                    dups.push((fd, Some(source)));
+
+                    continue;
+                }
+
+                // `>&file` is `&>file`
+                if operator == Operator::LessAnd {
+                    let error = io::Error::new(io::ErrorKind::InvalidInput, "bad file descriptor");
+
+                    return Err((target, error));
+                }
+            }
+
+            let mut options = std::fs::OpenOptions::new();
+
+            match operator {
+                Operator::Less => options.read(true),
+                Operator::DoubleGreat => options.append(true).create(true),
+                _ => options.write(true).create(true).truncate(true),
+            };
+
+            let file = match options.open(self.expand_path(&target)) {
+                Ok(file) => file,
+                Err(error) => return Err((target, error)),
+            };
+
+            let source = file.as_raw_fd();
+
+            match operator {
+                Operator::AndGreat | Operator::GreatAnd => {
+                    dups.push((1, Some(source)));
+                    dups.push((2, Some(source)));
+                }
+                _ => dups.push((fd, Some(source))),
+            }
+
+            files.push(file);
+        }
+
+        Ok((files, dups))
+    }
+
+    /// Returns the content of a here-document, `<<`, or here-string, `<<<`.
+    ///
+    /// The body of a here-document is expanded as if within double quotes, unless its delimiter
+    /// was quoted. A here-string is the expanded target followed by a newline.
+    #[inline]
+    fn here_document(&mut self, redirect: &Redirect<'_>) -> io::Result<String> {
+        if let Some(heredoc) = &redirect.heredoc {
+            let text = heredoc.text();
+
+            if !heredoc.expand {
+                return Ok(text.into_owned());
+            }
+
+            return expand::parts(self, Parts::quoted(Quote::DoubleQuote, &text));
+        }
+
+        match &redirect.target {
+            Some(target) if redirect.operator == Operator::TripleLess => {
+                let mut content = expand::value(self, target)?;
+
+                content.push('\n');
+
+                Ok(content)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "missing here-document",
+            )),
+        }
+    }
+
+    /// Have `process` join the process group `pgid`, or lead a new one when `pgid` is 0, and
+    /// take the terminal if it is to run in the foreground.
+    ///
+    /// The terminal is taken before executing, as spawning only returns once the program runs,
+    /// by which time it may have read from the terminal, and been stopped. Should executing fail,
+    /// the shell must take the terminal back.
+    #[inline]
+    fn job_control(&self, process: &mut Command, pgid: libc::pid_t, foreground: bool) {
+        let session = match &self.session {
+            Some(session) => session,
+            // without a terminal, there is no job control
+            None => return reset_signals(process),
+        };
+
+        let tty = foreground.then(|| session.as_raw_fd());
+
+        // SAFETY: only async-signal-safe functions are called.
+        unsafe {
+            process.pre_exec(move || {
+                libc::setpgid(0, pgid);
+
+                // while `SIGTTOU` is still ignored
+                if let Some(tty) = tty {
+                    libc::tcsetpgrp(tty, libc::getpgrp());
+                }
+
+                Ok(())
+            });
+        }
+
+        reset_signals(process);
+    }
+
+    /// Give the terminal back to the shell's process group.
+    #[inline]
+    fn take_terminal(&self) {
+        if let Some(session) = &self.session {
+            let _ = session.set_foreground(self.pgid);
+        }
+    }
+
+    /// Spawn each stage of a pipeline as a job in its own process group, connecting each
+    /// stdout to the next stdin, and wait for the job in the foreground, or announce it when in
+    /// the background. `string` is the source of the pipeline, and `run` runs the subshell of
+    /// the stage at an index.
+    ///
+    /// Commands which fail to spawn are reported, and the next command receives no input. The
+    /// status of the pipeline is the status of the last command. A lone command which fails to
+    /// spawn is not reported, the error is returned instead.
+    #[inline]
+    pub async fn spawn(
+        &mut self,
+        stages: &[Stage<'_, '_>],
+        string: &str,
+        background: bool,
+        run: &dyn Fn(&mut Context, usize) -> ExitStatus,
+    ) -> io::Result<io::Result<ExitStatus>> {
+        self.disable_raw().await?;
+
+        let last = stages.len().saturating_sub(1);
+        let mut processes = Vec::with_capacity(stages.len());
+        let mut stdin: Option<OwnedFd> = None;
+        let mut status = None;
+        let mut pgid = 0;
+
+        for (index, stage) in stages.iter().enumerate() {
+            let spawned = match stage {
+                Stage::Program(command) => {
+                    let mut process = match self.process(command) {
+                        Ok(process) => process,
+                        Err((target, error)) => {
+                            self.report(&target, &error).await?;
+                            status = Some(exit_status(1));
+
+                            continue;
+                        }
+                    };
+
+                    self.job_control(&mut process, pgid, !background);
+
+                    if index != 0 {
+                        process.stdin(stdin.take().map_or_else(Stdio::null, Stdio::from));
+                    }
+
+                    if index != last {
+                        process.stdout(Stdio::piped());
+                    }
+
+                    let _files = match self.redirect(&mut process, &command.redirects) {
+                        Ok(files) => files,
+                        Err((target, error)) => {
+                            self.report(&target, &error).await?;
+                            status = Some(exit_status(1));
+
+                            continue;
+                        }
+                    };
+
+                    match process.spawn() {
+                        Ok(mut child) => {
+                            let stdout = child.stdout.take().map(OwnedFd::from);
+
+                            Ok((child.id() as libc::pid_t, stdout))
+                        }
+                        Err(error) => Err((command.program.as_str(), error)),
+                    }
+                }
+                Stage::Subshell(redirects) => {
+                    let (_files, redirects) = match self.open_redirects(redirects) {
+                        Ok(redirects) => redirects,
+                        Err((target, error)) => {
+                            self.report(&target, &error).await?;
+                            status = Some(exit_status(1));
+
+                            continue;
+                        }
+                    };
+
+                    let stdin = match stdin.take() {
+                        Some(stdin) => Some(stdin),
+                        None if index != 0 => Some(std::fs::File::open("/dev/null")?.into()),
+                        None => None,
+                    };
+
+                    let (reader, writer) = if index != last {
+                        let (reader, writer) = pipe()?;
+
+                        (Some(reader), Some(writer))
+                    } else {
+                        (None, None)
+                    };
+
+                    // the pipes first, so the redirections of the stage replace them
+                    let dups: Vec<_> = [
+                        (libc::STDIN_FILENO, stdin.as_ref().map(AsRawFd::as_raw_fd)),
+                        (libc::STDOUT_FILENO, writer.as_ref().map(AsRawFd::as_raw_fd)),
+                    ]
+                    .into_iter()
+                    .filter(|(_fd, source)| source.is_some())
+                    .chain(redirects)
+                    .collect();
+
+                    match self.fork(Some((pgid, !background)), &dups, |context| {
+                        run(context, index)
+                    }) {
+                        Ok(pid) => Ok((pid, reader.map(OwnedFd::from))),
+                        Err(error) => Err((string, error)),
+                    }
+                }
+            };
+
+            match spawned {
+                Ok((pid, stdout)) => {
+                    if pgid == 0 {
+                        pgid = pid;
+                    }
+
+                    // the child does the same, whichever runs first avoids the race
+                    if self.is_interactive() {
+                        // SAFETY: `setpgid` takes no pointers, and `pid` is a child not yet
+                        // waited for, so it can't have been reused.
+                        unsafe {
+                            libc::setpgid(pid, pgid);
+                        }
+                    }
+
+                    stdin = stdout;
+                    status = None;
+                    processes.push(Process::new(pid));
+                }
+                Err((_target, error)) if last == 0 => {
+                    self.take_terminal();
+                    self.enable_raw().await?;
+
+                    return Ok(Err(error));
+                }
+                Err((target, error)) => {
+                    // the child led its own process group, taking the terminal, before failing
+                    // to execute
+                    if pgid == 0 {
+                        self.take_terminal();
+                    }
+
+                    self.report(target, &error).await?;
+                    status = Some(exit_status(127));
+                }
+            }
+        }
+
+        let status = if processes.is_empty() {
+            self.take_terminal();
+
+            status.unwrap_or_else(|| exit_status(0))
+        } else {
+            let command = string.trim().into();
+            let pid = processes.last().map(|process| process.pid).unwrap_or(pgid);
+            let id = self.jobs.insert(pgid, processes, command, status);
+
+            if background {
+                if self.is_interactive() {
+                    let edit = format!("[{id}] {pid}\r\n");
+
+                    self.write_all(edit.as_bytes()).await?;
+                }
+
+                exit_status(0)
+            } else {
+                self.foreground(id).await?
+            }
+        };
+
+        self.enable_raw().await?;
+
+        Ok(Ok(status))
+    }
+
+    /// Fork the shell, running `run` in the child, a subshell, which exits with the status it
+    /// returns, and returning its pid.
+    ///
+    /// The subshell joins the process group of `job`, or leads a new one when it is 0, taking the
+    /// terminal if it runs in the foreground, as programs do, then duplicates `dups`, as
+    /// redirections do, closing the other descriptors it inherited. It has no terminal of its
+    /// own, so builtins write to its stdout, and no jobs.
+    #[inline]
+    fn fork<F>(
+        &mut self,
+        job: Option<(libc::pid_t, bool)>,
+        dups: &[(RawFd, Option<RawFd>)],
+        run: F,
+    ) -> io::Result<libc::pid_t>
+    where
+        F: FnOnce(&mut Self) -> ExitStatus,
+    {
+        // SAFETY: the child carries on with only this thread, and exits without returning to
+        // the runtime, or anything else the other threads were in the middle of.
+        let pid = unsafe { libc::fork() };
+
+        if pid != 0 {
+            return match pid {
+                -1 => Err(io::Error::last_os_error()),
+                pid => Ok(pid),
+            };
+        }
+
+        if let (Some(session), Some((pgid, foreground))) = (&self.session, job) {
+            // SAFETY: `setpgid` takes no pointers, and only moves the subshell itself.
+            unsafe {
+                libc::setpgid(0, pgid);
+            }
+
+            // while `SIGTTOU` is still ignored
+            if foreground {
+                // SAFETY: `getpgrp` has no arguments, and can't fail.
+                let _ = session.set_foreground(unsafe { libc::getpgrp() });
+            }
+        }
+
+        for signal in JOB_SIGNALS {
+            // SAFETY: restoring the default action installs no handler.
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+            }
+        }
+
+        let status = match dup_all(dups) {
+            Ok(()) => {
+                let keep: Vec<_> = dups.iter().map(|(fd, _source)| *fd).collect();
+
+                close_inherited(&keep);
+                // the terminal is left to the parent, dropping it would also remove it from the
+                // reactor they share
+                mem::forget(self.session.take());
+                self.jobs = Jobs::new();
+
+                run(self)
+            }
+            Err(error) => {
+                let edit = format!("elysh: {}\n", error_message(&error));
+
+                let _ = io::stderr().write_all(edit.as_bytes());
+
+                exit_status(1)
+            }
+        };
+
+        let _ = io::stdout().flush();
+
+        // SAFETY: `_exit` takes no pointers, and skips the exit handlers of the parent, whose
+        // state isn't the subshell's to clean up.
+        unsafe { libc::_exit(status_code(status)) }
+    }
+
+    /// Run a command list for command substitution, `$(...)`, in a subshell, returning its
+    /// output without trailing newlines.
+    ///
+    /// The subshell runs within the shell's process group, builtins and functions included, and
+    /// its exit status becomes `$?`. Errors within it are reported by the subshell itself, as
+    /// they would be by the shell.
+    #[inline]
+    pub fn substitute(&mut self, list: &List<'_>) -> io::Result<String> {
+        let (mut reader, writer) = pipe()?;
+        // the subshell reports errors to the terminal, which may be in raw mode
+        let termios = self
+            .session
+            .as_ref()
+            .and_then(|session| session.termios().ok());
+
+        if let Some(session) = &self.session {
+            let _ = session.set_cooked();
+        }
+
+        let dups = [(libc::STDOUT_FILENO, Some(writer.as_raw_fd()))];
+        let pid = self.fork(None, &dups, |context| crate::run_subshell(context, list));
+
+        drop(writer);
+
+        let mut output = Vec::new();
+        let status = pid.and_then(|pid| {
+            // read until every command of the list is done with the pipe, so none block on it
+            let read = reader.read_to_end(&mut output);
+            let status = wait_pid(pid)?;
+
+            read.map(|_len| status)
+        });
+
+        if let (Some(session), Some(termios)) = (&self.session, termios) {
+            let _ = session.set_termios(&termios);
+        }
+
+        self.set_status(status?);
+
+        let output = String::from_utf8_lossy(&output);
+
+        Ok(output.trim_end_matches('\n').into())
+    }
+
+    /// Wait for a job to exit or stop.
+    #[inline]
+    fn wait_job(&mut self, id: usize) {
+        while let Some(job) = self.jobs.get(id) {
+            if job.state() != State::Running {
+                break;
+            }
+
+            let mut raw = 0;
+            // without job control, processes share the shell's process group
+            let target = if self.is_interactive() { -job.pgid } else { -1 };
+            // SAFETY: `raw` is a valid pointer.
+            let pid = unsafe { libc::waitpid(target, &mut raw, libc::WUNTRACED) };
+
+            if pid == -1 {
+                if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+
+                // nothing left to wait for
+                break;
+            }
+
+            self.jobs.update(pid, ExitStatus::from_raw(raw));
+        }
+    }
+
+    /// Collect the status of jobs which changed state in the background, without waiting.
+    #[inline]
+    pub fn reap_jobs(&mut self) {
+        loop {
+            let mut raw = 0;
+            // SAFETY: `raw` is a valid pointer.
+            let pid = unsafe {
+                libc::waitpid(
+                    -1,
+                    &mut raw,
+                    libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED,
+                )
+            };
+
+            if pid <= 0 {
+                break;
+            }
+
+            self.jobs.update(pid, ExitStatus::from_raw(raw));
+        }
+    }
+
+    /// Give the terminal to a job and wait for it to exit or stop, then take the terminal back.
+    ///
+    /// Jobs which exit are removed, jobs which stop are reported and become the current job.
+    #[inline]
+    async fn foreground(&mut self, id: usize) -> io::Result<ExitStatus> {
+        if let (Some(session), Some(job)) = (&self.session, self.jobs.get(id)) {
+            let _ = session.set_foreground(job.pgid);
+        }
+
+        self.wait_job(id);
+
+        let termios = match &self.session {
+            Some(session) => {
+                let _ = session.set_foreground(self.pgid);
+
+                session.termios().ok()
+            }
+            None => None,
+        };
+
+        let job = match self.jobs.get_mut(id) {
+            Some(job) => job,
+            None => return Ok(exit_status(0)),
+        };
+
+        if job.state() == State::Stopped {
+            job.termios = termios;
+
+            let signal = job
+                .processes
+                .iter()
+                .find_map(|process| process.status?.stopped_signal())
+                .unwrap_or(libc::SIGTSTP);
+
+            let edit = format!("\r\n{job}\r\n");
+
+            self.jobs.make_current(id);
+            self.write_all(edit.as_bytes()).await?;
+
+            Ok(exit_status(128 + signal))
+        } else {
+            let status = job.status().unwrap_or_else(|| exit_status(0));
+
+            self.jobs.remove(id);
+
+            Ok(status)
+        }
+    }
+
+    /// Continue a stopped job, in the foreground, waiting for it, or in the background.
+    #[inline]
+    pub async fn resume(&mut self, id: usize, foreground: bool) -> io::Result<ExitStatus> {
+        let job = match self.jobs.get_mut(id) {
+            Some(job) => job,
+            None => return Ok(exit_status(1)),
+        };
+
+        let edit = if foreground {
+            format!("{}\r\n", job.command)
+        } else {
+            format!("[{}] {} &\r\n", job.id, job.command)
+        };
+
+        let pgid = job.pgid;
+        let termios = job.termios.take();
+
+        job.continued();
+        self.jobs.make_current(id);
+        self.write_all(edit.as_bytes()).await?;
+
+        if !foreground {
+            // SAFETY: `kill` takes no pointers, and signals only the job's process group, as
+            // `-pgid` is negative. It fails harmlessly if the group has already exited.
+            unsafe {
+                libc::kill(-pgid, libc::SIGCONT);
+            }
+
+            return Ok(exit_status(0));
+        }
+
+        self.disable_raw().await?;
+
+        if let Some(session) = &self.session {
+            if let Some(termios) = termios {
+                let _ = session.set_termios(&termios);
+            }
+
+            let _ = session.set_foreground(pgid);
+        }
+
+        // SAFETY: as above, this only signals the job's process group.
+        unsafe {
+            libc::kill(-pgid, libc::SIGCONT);
+        }
+
+        let status = self.foreground(id).await?;
+
+        self.enable_raw().await?;
+
+        Ok(status)
+    }
+
+    /// Wait for a job in the background to exit or stop, or every job when `id` is `None`.
+    #[inline]
+    pub fn wait(&mut self, id: Option<usize>) -> ExitStatus {
+        let ids: Vec<usize> = match id {
+            Some(id) => vec![id],
+            None => self.jobs.iter().map(|job| job.id).collect(),
+        };
+
+        let mut status = exit_status(0);
+
+        for id in ids {
+            self.wait_job(id);
+
+            if let Some(job) = self.jobs.get(id) {
+                if job.state() == State::Done {
+                    status = job.status().unwrap_or_else(|| exit_status(0));
+                    self.jobs.remove(id);
+                }
+            }
+        }
+
+        status
+    }
+
+    /// Collect the status of jobs in the background, removing those which are done, and
+    /// returning a notice for each of them.
+    #[inline]
+    pub fn finished_jobs(&mut self) -> String {
+        self.reap_jobs();
+
+        let done: Vec<usize> = self
+            .jobs
+            .iter()
+            .filter(|job| job.state() == State::Done)
+            .map(|job| job.id)
+            .collect();
+
+        let mut edit = String::new();
+
+        for id in done {
+            if let Some(job) = self.jobs.remove(id) {
+                let _ = write!(edit, "{job}\r\n");
+            }
+        }
+
+        edit
+    }
+
+    /// Write the job table to the terminal, forgetting jobs which are done.
+    #[inline]
+    pub async fn list_jobs(&mut self) -> io::Result<()> {
+        self.reap_jobs();
+
+        let mut edit = String::new();
+
+        for job in self.jobs.iter() {
+            let _ = write!(edit, "{job}\r\n");
+        }
+
+        let done: Vec<usize> = self
+            .jobs
+            .iter()
+            .filter(|job| job.state() == State::Done)
+            .map(|job| job.id)
+            .collect();
+
+        for id in done {
+            self.jobs.remove(id);
+        }
+
+        self.write_all(edit.as_bytes()).await?;
+
+        Ok(())
     }
 
     #[inline]
@@ -410,7 +2259,7 @@ impl Context {
         let current_dir = self.shorten_path(&self.current_dir);
         let edit = format!("\r\n \x1b[K{}\r\n", current_dir.display());
 
-        self.session.write_all(edit.as_bytes()).await?;
+        self.write_all(edit.as_bytes()).await?;
 
         Ok(())
     }
@@ -0,0 +1,408 @@
+use crate::context::Context;
+use crate::{glob, pattern};
+use elysh_syntax::{Arg, List, Param, ParamOp, Part, Parts, Quote, Value};
+use std::io;
+
+/// Field separators when `$IFS` is unset.
+const IFS: &str = " \t\n";
+
+/// Expand an argument into a string.
+#[inline]
+pub fn arg(context: &mut Context, arg: &Arg<'_>) -> io::Result<String> {
+    match arg.value() {
+        Some(value) => self::value(context, &value),
+        None => Ok(arg.as_str().into()),
+    }
+}
+
+/// Expand an argument into fields.
+///
+/// Braces of unquoted words are expanded first, then each resulting word is expanded on its own,
+/// quoted strings joined within it included, i.e. `--opt="a b"` is a single field. The output of
+/// command substitution outside of quotes is split into fields on the characters of `$IFS`, so
+/// `kill $(pgrep foo)` passes each pid as an argument. Output which is only separators produces
+/// no fields.
+///
+/// Unquoted words then have a leading `~` replaced with the home directory, and each field which
+/// is a glob is replaced by the paths it matches.
+///
+/// `"$@"` expands to each positional parameter as its own field, and `$@` and `$*` outside of
+/// quotes are split like command substitution.
+#[inline]
+pub fn fields(context: &mut Context, arg: &Arg<'_>) -> io::Result<Vec<String>> {
+    let value = match arg.value() {
+        Some(value) => value,
+        None => return Ok(vec![arg.as_str().into()]),
+    };
+
+    let ifs = context.var("IFS").unwrap_or_else(|| String::from(IFS));
+
+    if let Some(list) = backticks(context, &value) {
+        let output = context.substitute(&list)?;
+        let mut fields = Vec::new();
+        let mut field = None;
+
+        split(&output, &ifs, &mut fields, &mut field);
+        fields.extend(field);
+
+        return Ok(fields.into_iter().map(|field| field.string).collect());
+    }
+
+    if let Value::Quoted(Quote::DoubleQuote, "$@" | "${@}") = value {
+        return Ok(context.positional().to_vec());
+    }
+
+    if value.is_quoted() {
+        return Ok(vec![self::value(context, &value)?]);
+    }
+
+    let mut words = Vec::new();
+
+    let braces = value
+        .braces()
+        .map_err(|_error| io::Error::new(io::ErrorKind::Other, "range is too long"))?;
+
+    for word in braces {
+        words.extend(self::word(context, &word, &ifs)?);
+    }
+
+    Ok(words)
+}
+
+/// A field being built, along with the glob pattern it forms.
+#[derive(Default)]
+struct Field {
+    string: String,
+    /// The field with escaped glob characters still escaped.
+    pattern: String,
+}
+
+impl Field {
+    #[inline]
+    fn push_str(&mut self, string: &str) {
+        self.string.push_str(string);
+        self.pattern.push_str(string);
+    }
+
+    /// Push quoted text, which only matches itself as a glob.
+    #[inline]
+    fn push_quoted(&mut self, string: &str) {
+        self.string.push_str(string);
+        self.pattern.push_str(&pattern::escape(string));
+    }
+}
+
+/// Expand a word into fields, splitting, then expanding `~` and globs.
+///
+/// Quoted strings within the word are joined to the fields around them, unsplit.
+#[inline]
+fn word(context: &mut Context, word: &str, ifs: &str) -> io::Result<Vec<String>> {
+    let mut fields = Vec::new();
+    // the field being built, `None` until something is added to it, even a quoted empty string
+    let mut field: Option<Field> = None;
+
+    for segment in Value::Word(word).segments() {
+        if let Some(list) = backticks(context, &segment) {
+            let output = context.substitute(&list)?;
+
+            split(&output, ifs, &mut fields, &mut field);
+
+            continue;
+        }
+
+        // each parameter is a field of its own, the first, and last, joined to those around them
+        if let Value::Quoted(Quote::DoubleQuote, "$@" | "${@}") = segment {
+            for (index, param) in context.positional().iter().enumerate() {
+                if index > 0 {
+                    fields.extend(field.take());
+                }
+
+                field.get_or_insert_with(Field::default).push_quoted(param);
+            }
+
+            continue;
+        }
+
+        if segment.is_quoted() {
+            let string = self::value(context, &segment)?;
+
+            field
+                .get_or_insert_with(Field::default)
+                .push_quoted(&string);
+
+            continue;
+        }
+
+        self::unquoted(context, &segment, ifs, &mut fields, &mut field)?;
+    }
+
+    fields.extend(field);
+
+    if word.starts_with('~') {
+        if let Some(first) = fields.first_mut() {
+            first.string = tilde(context, &first.string);
+            first.pattern = tilde(context, &first.pattern);
+        }
+    }
+
+    let mut words = Vec::with_capacity(fields.len());
+
+    for field in fields {
+        if !pattern::is_pattern(&field.pattern) {
+            words.push(field.string);
+
+            continue;
+        }
+
+        let matches = glob::expand(&field.pattern);
+
+        if !matches.is_empty() {
+            words.extend(matches);
+        } else if context.options.failglob {
+            return Err(io::Error::new(io::ErrorKind::Other, "no matches found"));
+        } else if !context.options.nullglob {
+            words.push(field.string);
+        }
+    }
+
+    Ok(words)
+}
+
+/// Expand unquoted text within a word, splitting the output of command substitution, and `$@`,
+/// into `fields`, and joining the rest to `field`.
+#[inline]
+fn unquoted(
+    context: &mut Context,
+    segment: &Value<'_>,
+    ifs: &str,
+    fields: &mut Vec<Field>,
+    field: &mut Option<Field>,
+) -> io::Result<()> {
+    for part in segment.parts() {
+        match part {
+            Part::Literal(literal) => {
+                let field = field.get_or_insert_with(Field::default);
+
+                field.string.push_str(&literal.unescape());
+                field.pattern.push_str(&literal.pattern());
+            }
+            Part::Command(list) => {
+                let output = context.substitute(&list)?;
+
+                split(&output, ifs, fields, field);
+            }
+            Part::Param(param) if matches!(param.name, "@" | "*") => {
+                let string = self::param(context, &param)?;
+
+                split(&string, ifs, fields, field);
+            }
+            part => {
+                let string = self::part(context, part)?;
+
+                field.get_or_insert_with(Field::default).push_str(&string);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace a leading `~` with the home directory.
+#[inline]
+pub fn tilde(context: &Context, word: &str) -> String {
+    if word == "~" || word.starts_with("~/") {
+        let path = context.expand_path(word);
+        let mut path = path.to_string_lossy().into_owned();
+
+        // `expand_path` drops any trailing slash, which matters to globs
+        if word.len() > 1 && word.ends_with('/') {
+            path.push('/');
+        }
+
+        path
+    } else {
+        word.into()
+    }
+}
+
+/// Split `output` on the characters of `ifs`, joining the first word to `field`, and
+/// pushing each field it ends.
+#[inline]
+fn split(output: &str, ifs: &str, fields: &mut Vec<Field>, field: &mut Option<Field>) {
+    let mut words = output.split(|character| ifs.contains(character));
+
+    if let Some(word) = words.next().filter(|word| !word.is_empty()) {
+        field.get_or_insert_with(Field::default).push_str(word);
+    }
+
+    for word in words {
+        fields.extend(field.take());
+
+        if !word.is_empty() {
+            field.get_or_insert_with(Field::default).push_str(word);
+        }
+    }
+}
+
+/// Expand a value into a string.
+///
+/// Nothing within single quotes is expanded.
+#[inline]
+pub fn value(context: &mut Context, value: &Value<'_>) -> io::Result<String> {
+    let mut string = String::new();
+
+    for segment in value.segments() {
+        match backticks(context, &segment) {
+            Some(list) => string.push_str(&context.substitute(&list)?),
+            None => string.push_str(&parts(context, segment.parts())?),
+        }
+    }
+
+    Ok(string)
+}
+
+/// Expand a value into a glob pattern, as the patterns of `case`.
+///
+/// Quoted, and escaped characters only match themselves.
+#[inline]
+pub fn pattern(context: &mut Context, value: &Value<'_>) -> io::Result<String> {
+    let mut pattern = String::new();
+
+    for segment in value.segments() {
+        if segment.is_quoted() {
+            pattern.push_str(&pattern::escape(&self::value(context, &segment)?));
+
+            continue;
+        }
+
+        for part in segment.parts() {
+            match part {
+                Part::Literal(literal) => pattern.push_str(&literal.pattern()),
+                part => pattern.push_str(&self::part(context, part)?),
+            }
+        }
+    }
+
+    Ok(pattern)
+}
+
+/// Expand each part, concatenating the results.
+#[inline]
+pub fn parts(context: &mut Context, parts: Parts<'_>) -> io::Result<String> {
+    let mut string = String::new();
+
+    for part in parts {
+        string.push_str(&self::part(context, part)?);
+    }
+
+    Ok(string)
+}
+
+/// Expand a single part.
+#[inline]
+pub fn part(context: &mut Context, part: Part<'_>) -> io::Result<String> {
+    match part {
+        Part::Literal(literal) => Ok(literal.unescape().into_owned()),
+        Part::Param(param) => self::param(context, &param),
+        Part::Command(list) => context.substitute(&list),
+    }
+}
+
+/// Returns the command within backticks, when the `backticks` option is on.
+#[inline]
+fn backticks<'a>(context: &Context, value: &Value<'a>) -> Option<List<'a>> {
+    if context.options.backticks {
+        value.command()
+    } else {
+        None
+    }
+}
+
+/// Expand a parameter against the shell variables.
+///
+/// `${name:?word}` fails with `word` as the message when `name` is unset or null.
+#[inline]
+pub fn param(context: &mut Context, param: &Param<'_>) -> io::Result<String> {
+    let value = context.var(param.name);
+    let is_set = |colon: bool| match &value {
+        Some(value) => !(colon && value.is_empty()),
+        None => false,
+    };
+
+    let string = match param.op {
+        ParamOp::Plain => value.unwrap_or_default(),
+        ParamOp::Length => value
+            .map(|value| value.chars().count())
+            .unwrap_or_default()
+            .to_string(),
+        ParamOp::Default { colon, word } => {
+            if is_set(colon) {
+                value.unwrap_or_default()
+            } else {
+                parts(context, Parts::new(word))?
+            }
+        }
+        ParamOp::Assign { colon, word } => {
+            if is_set(colon) {
+                value.unwrap_or_default()
+            } else {
+                let word = parts(context, Parts::new(word))?;
+
+                context.set_var(param.name, word.clone())?;
+
+                word
+            }
+        }
+        ParamOp::Error { colon, word } => {
+            if is_set(colon) {
+                value.unwrap_or_default()
+            } else {
+                let word = parts(context, Parts::new(word))?;
+                let message = if word.is_empty() {
+                    String::from("parameter null or not set")
+                } else {
+                    word
+                };
+
+                // a script can't go on without it
+                if !context.is_interactive() {
+                    crate::fail(param.name, &message, 1);
+                }
+
+                return Err(io::Error::new(io::ErrorKind::Other, message));
+            }
+        }
+        ParamOp::Alternate { colon, word } => {
+            if is_set(colon) {
+                parts(context, Parts::new(word))?
+            } else {
+                String::new()
+            }
+        }
+        ParamOp::RemovePrefix { longest, pattern } => {
+            let value = value.unwrap_or_default();
+            let pattern = parts(context, Parts::new(pattern))?;
+
+            pattern::remove_prefix(&value, &pattern, longest).into()
+        }
+        ParamOp::RemoveSuffix { longest, pattern } => {
+            let value = value.unwrap_or_default();
+            let pattern = parts(context, Parts::new(pattern))?;
+
+            pattern::remove_suffix(&value, &pattern, longest).into()
+        }
+        ParamOp::Replace {
+            all,
+            pattern,
+            string,
+        } => {
+            let value = value.unwrap_or_default();
+            let pattern = parts(context, Parts::new(pattern))?;
+            let string = parts(context, Parts::new(string))?;
+
+            pattern::replace(&value, &pattern, &string, all)
+        }
+    };
+
+    Ok(string)
+}
@@ -0,0 +1,99 @@
+use crate::pattern;
+use std::fs;
+use std::path::Path;
+
+/// Expand a glob pattern into the paths which match it, sorted.
+///
+/// Each component of the pattern is matched against the entries of a directory, and `**` matches
+/// any number of directories, including none. Names beginning with `.` are only matched by a
+/// component which also begins with `.`.
+#[inline]
+pub fn expand(pattern: &str) -> Vec<String> {
+    let components: Vec<&str> = pattern.split('/').collect();
+    let mut matches = Vec::new();
+
+    walk("", &components, &mut matches);
+
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// Match `components` against the directory `prefix`, which is empty or ends with `/`.
+#[inline]
+fn walk(prefix: &str, components: &[&str], matches: &mut Vec<String>) {
+    let (component, rest) = match components.split_first() {
+        Some(split) => split,
+        None => {
+            matches.push(prefix.into());
+
+            return;
+        }
+    };
+
+    if *component == "**" {
+        walk(prefix, rest, matches);
+
+        for name in read_dir(prefix) {
+            let path = format!("{prefix}{name}");
+
+            // don't follow symlinks, they may loop
+            let is_dir = fs::symlink_metadata(&path)
+                .map(|metadata| metadata.is_dir())
+                .unwrap_or(false);
+
+            if is_dir && !name.starts_with('.') {
+                walk(&format!("{path}/"), components, matches);
+            }
+        }
+
+        return;
+    }
+
+    // nothing to match, and no need to read the directory
+    if !pattern::is_pattern(component) {
+        let path = format!("{prefix}{component}");
+
+        if rest.is_empty() {
+            if fs::symlink_metadata(&path).is_ok() {
+                matches.push(path);
+            }
+        } else {
+            walk(&format!("{path}/"), rest, matches);
+        }
+
+        return;
+    }
+
+    for name in read_dir(prefix) {
+        if name.starts_with('.') && !component.starts_with('.') {
+            continue;
+        }
+
+        if !pattern::matches(component, &name) {
+            continue;
+        }
+
+        let path = format!("{prefix}{name}");
+
+        if rest.is_empty() {
+            matches.push(path);
+        } else if Path::new(&path).is_dir() {
+            walk(&format!("{path}/"), rest, matches);
+        }
+    }
+}
+
+/// Returns the names of the entries of `dir`, the current directory when empty.
+#[inline]
+fn read_dir(dir: &str) -> Vec<String> {
+    let dir = if dir.is_empty() { "." } else { dir };
+
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect(),
+        Err(_error) => Vec::new(),
+    }
+}
@@ -1,29 +1,193 @@
+pub use record::Record;
+pub use settings::{Duplicates, Settings};
+
+use crate::context::shell_pid;
 use core::cmp::Ordering;
+use elysh_edit::Edit;
+use file::{Appended, HistoryFile, Lock};
+use std::collections::HashSet;
 use std::io;
-use std::path::{Path, PathBuf};
-use tokio::fs;
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+
+mod file;
+mod record;
+mod settings;
+
+/// How many records a session appends to the history file before compacting it.
+const COMPACT_AFTER: usize = 100;
 
+/// Percentage of `HISTFILEBYTES` the file is compacted to, leaving room for appends before it
+/// grows past the limit again.
+const COMPACT_PERCENT: u64 = 90;
+
+/// An incremental search through history, begun by Ctrl-R.
 #[derive(Debug)]
-pub struct History {
-    history: Vec<String>,
+pub struct Search {
+    /// The text being searched for.
+    query: String,
+    /// Index of the item matching, the oldest being `0`.
+    found: Option<usize>,
+    /// Did the last change to the query find nothing?
+    failed: bool,
+    /// Position within history, and the line being edited, restored when the search is cancelled.
     position: isize,
+    edit: Edit,
 }
 
-impl History {
-    const NEW: Self = {
-        let history = Vec::new();
-        let position = 0;
+impl Search {
+    #[inline]
+    pub fn new(position: isize, edit: Edit) -> Self {
+        Self {
+            query: String::new(),
+            found: None,
+            failed: false,
+            position,
+            edit,
+        }
+    }
+
+    /// Returns the text being searched for.
+    #[inline]
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Returns the index of the item matching.
+    #[inline]
+    pub fn found(&self) -> Option<usize> {
+        self.found
+    }
+
+    /// Did the last change to the query find nothing?
+    #[inline]
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Returns the position within history, and the line being edited, before the search began.
+    #[inline]
+    pub fn original(&self) -> (isize, &Edit) {
+        (self.position, &self.edit)
+    }
 
-        Self { history, position }
-    };
+    /// Append a character to the query, narrowing the search from the item matching.
+    #[inline]
+    pub fn push(&mut self, history: &History, character: char) {
+        self.query.push(character);
+
+        let before = self.found.map_or(history.len(), |index| index + 1);
+
+        self.update(history.rfind(&self.query, before));
+    }
+
+    /// Remove the last character from the query, searching again from the newest item.
+    #[inline]
+    pub fn pop(&mut self, history: &History) {
+        self.query.pop();
+
+        self.failed = false;
+        self.found = if self.query.is_empty() {
+            None
+        } else {
+            history.rfind(&self.query, history.len())
+        };
+    }
+
+    /// Step to the next older item matching, skipping those the same as the item matching.
+    #[inline]
+    pub fn older(&mut self, history: &History) {
+        let mut before = self.found.unwrap_or(history.len());
+        let current = self.found.and_then(|index| history.item(index));
+
+        let found = loop {
+            match history.rfind(&self.query, before) {
+                Some(index) if history.item(index) == current => before = index,
+                found => break found,
+            }
+        };
+
+        self.update(found);
+    }
+
+    /// Step to the next newer item matching, skipping those the same as the item matching.
+    #[inline]
+    pub fn newer(&mut self, history: &History) {
+        let Some(mut after) = self.found else {
+            return;
+        };
+
+        let current = history.item(after);
+
+        let found = loop {
+            match history.find(&self.query, after) {
+                Some(index) if history.item(index) == current => after = index,
+                found => break found,
+            }
+        };
+
+        self.update(found);
+    }
 
+    /// Keep the item matching when nothing else matches.
+    #[inline]
+    fn update(&mut self, found: Option<usize>) {
+        self.failed = found.is_none() && !self.query.is_empty();
+
+        if found.is_some() {
+            self.found = found;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct History {
+    history: Vec<Record>,
+    position: isize,
+    /// Indices of the items stepped to by prefix, the oldest last, so stepping back retraces them.
+    stepped: Vec<usize>,
+    /// When the command last pushed began, until it finishes.
+    started: Option<Instant>,
+    hostname: String,
+    /// Identifies this shell within the records it pushes.
+    session: String,
+    file: HistoryFile,
+    /// How many records were appended to the file since it was compacted.
+    appended: usize,
+}
+
+impl History {
     /// Construct a new history!
+    ///
+    /// A file of an earlier version is read as plain text, and upgraded.
     #[inline]
-    pub async fn new<P>(data_dir: P) -> Self
+    pub fn new<P>(data_dir: P) -> Self
     where
         P: AsRef<Path>,
     {
-        Self::load(data_dir).await.unwrap_or_else(|_| Self::NEW)
+        let mut file = HistoryFile::new(data_dir.as_ref());
+        let history = file
+            .lock()
+            .and_then(|lock| file.read_all(&lock))
+            .unwrap_or_default();
+        let appended = 0;
+        let position = 0;
+        let stepped = Vec::new();
+        let started = None;
+        let hostname = hostname();
+        // the start time, and process, tell sessions on this machine apart
+        let session = format!("{:x}-{:x}", unix_time(SystemTime::now()), shell_pid());
+
+        Self {
+            history,
+            position,
+            stepped,
+            started,
+            hostname,
+            session,
+            file,
+            appended,
+        }
     }
 
     /// Returns the count of history items.
@@ -38,10 +202,172 @@ impl History {
         self.position
     }
 
-    /// Append a new item to the history list.
+    /// Iterate each record, the oldest first.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Record> {
+        self.history.iter()
+    }
+
+    /// Append a command which is about to run within `cwd`, unless `settings` ignore it.
+    #[inline]
+    pub fn push(&mut self, text: String, cwd: &Path, settings: &Settings) {
+        if settings.ignores(&text) || settings.max_entries == 0 {
+            return;
+        }
+
+        match settings.duplicates {
+            Duplicates::IgnoreConsecutive
+                if self.history.last().map(|record| &record.text) == Some(&text) =>
+            {
+                return;
+            }
+            Duplicates::Erase => self.history.retain(|record| record.text != text),
+            _ => {}
+        }
+
+        let record = Record {
+            text,
+            start: Some(unix_time(SystemTime::now())),
+            duration: None,
+            status: None,
+            cwd: cwd.display().to_string(),
+            hostname: self.hostname.clone(),
+            session: self.session.clone(),
+        };
+
+        self.history.push(record);
+        self.started = Some(Instant::now());
+        self.trim(settings.max_entries);
+    }
+
+    /// Record how the command last pushed finished, and append it to the history file.
+    ///
+    /// When sharing, records other sessions appended meanwhile are kept too, before it.
+    #[inline]
+    pub fn finish(&mut self, status: i32, settings: &Settings) {
+        let (Some(started), Some(record)) = (self.started.take(), self.history.last_mut()) else {
+            return;
+        };
+
+        record.duration = Some(started.elapsed().as_millis() as u64);
+        record.status = Some(status);
+
+        let _ = self.append(settings);
+    }
+
+    /// Keep the records other sessions appended to the history file, when sharing.
+    #[inline]
+    pub fn sync(&mut self, settings: &Settings) {
+        if !settings.share {
+            return;
+        }
+
+        if let Ok(lock) = self.file.lock() {
+            if let Ok(appended) = self.file.read_appended(&lock) {
+                self.merge(appended, 0);
+                self.trim(settings.max_entries);
+            }
+        }
+    }
+
+    /// Remove each record `matches`, from memory, and the history file, returning how many.
+    ///
+    /// The command running is removed too if it matches, so isn't recorded as it finishes.
+    #[inline]
+    pub fn delete<F>(&mut self, matches: F) -> io::Result<usize>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let pending = self.history.last().map(|record| matches(&record.text));
+
+        if self.started.is_some() && pending == Some(true) {
+            self.started = None;
+        }
+
+        let len = self.history.len();
+
+        self.history.retain(|record| !matches(&record.text));
+        self.reset();
+
+        let mut removed = len - self.history.len();
+        let lock = self.file.lock()?;
+        let mut records = self.file.read_all(&lock)?;
+        let len = records.len();
+
+        records.retain(|record| !matches(&record.text));
+
+        if records.len() != len {
+            removed = removed.max(len - records.len());
+            self.file.rewrite(&lock, &records)?;
+        }
+
+        Ok(removed)
+    }
+
     #[inline]
-    pub fn push(&mut self, item: String) {
-        self.history.push(item);
+    fn append(&mut self, settings: &Settings) -> io::Result<()> {
+        let lock = self.file.lock()?;
+        let appended = self.file.read_appended(&lock)?;
+
+        // the file mustn't skip what others appended, even if they aren't kept
+        if settings.share {
+            self.merge(appended, 1);
+            self.trim(settings.max_entries);
+        }
+
+        if let Some(record) = self.history.last() {
+            self.file.append(&lock, record)?;
+            self.appended += 1;
+        }
+
+        if self.appended >= COMPACT_AFTER || self.file.len() > settings.max_bytes {
+            self.compact(&lock, settings)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the oldest records beyond `max_entries`.
+    #[inline]
+    fn trim(&mut self, max_entries: usize) {
+        let excess = self.history.len().saturating_sub(max_entries);
+
+        self.history.drain(..excess);
+    }
+
+    /// Keep records read from the history file, before the last `pending` of this session.
+    #[inline]
+    fn merge(&mut self, appended: Appended, pending: usize) {
+        let at = self.history.len().saturating_sub(pending);
+
+        match appended {
+            Appended::After(records) => {
+                let others = records
+                    .into_iter()
+                    .filter(|record| record.session != self.session);
+
+                self.history.splice(at..at, others);
+            }
+            // the file was compacted, and holds each record this session appended
+            Appended::All(records) => {
+                let pending = self.history.split_off(at);
+
+                self.history = records;
+                self.history.extend(pending);
+            }
+        }
+    }
+
+    /// Rewrite the history file from what it holds, within the limits of `settings`, dropping
+    /// lines left incomplete by sessions which crashed.
+    #[inline]
+    fn compact(&mut self, lock: &Lock, settings: &Settings) -> io::Result<()> {
+        let records = limit(self.file.read_all(lock)?, settings);
+
+        self.file.rewrite(lock, &records)?;
+        self.appended = 0;
+
+        Ok(())
     }
 
     /// Increment the position within the history list.
@@ -56,14 +382,92 @@ impl History {
         self.position = self.position.saturating_sub(1).max(-(self.len() as isize));
     }
 
+    /// Step to the next older item beginning with `prefix`, skipping those the same as an item
+    /// already stepped to.
+    ///
+    /// Stays put when there is none.
+    #[inline]
+    pub fn next_prefixed(&mut self, prefix: &str) {
+        let before = match self.position {
+            1.. => self.len().saturating_sub(self.position as usize),
+            _ => self.len(),
+        };
+
+        let found = self.history[..before].iter().rposition(|record| {
+            record.text.starts_with(prefix)
+                && !self
+                    .stepped
+                    .iter()
+                    .any(|index| self.item(*index) == Some(&record.text))
+        });
+
+        if let Some(index) = found {
+            self.stepped.push(index);
+            self.position = self.len().saturating_sub(index) as isize;
+        }
+    }
+
+    /// Step back to the item stepped to before the current by `next_prefixed`, or out of the
+    /// history list.
+    #[inline]
+    pub fn next_back_prefixed(&mut self) {
+        self.stepped.pop();
+        self.position = match self.stepped.last() {
+            Some(index) => self.len().saturating_sub(*index) as isize,
+            None => 0,
+        };
+    }
+
+    /// Move the position to the item at `index`, the oldest being `0`.
+    #[inline]
+    pub fn set_index(&mut self, index: usize) {
+        self.stepped.clear();
+        self.position = self.len().saturating_sub(index) as isize;
+    }
+
+    /// Set the position within the history list.
+    #[inline]
+    pub fn set_position(&mut self, position: isize) {
+        self.stepped.clear();
+        self.position = position;
+    }
+
+    /// Returns the item at `index`, the oldest being `0`.
+    #[inline]
+    pub fn item(&self, index: usize) -> Option<&str> {
+        self.history.get(index).map(|record| record.text.as_str())
+    }
+
+    /// Find the newest item older than `before` containing `query`.
+    #[inline]
+    pub fn rfind(&self, query: &str, before: usize) -> Option<usize> {
+        let before = before.min(self.len());
+
+        self.history[..before]
+            .iter()
+            .rposition(|record| record.text.contains(query))
+    }
+
+    /// Find the oldest item newer than `after` containing `query`.
+    #[inline]
+    pub fn find(&self, query: &str, after: usize) -> Option<usize> {
+        let after = after.saturating_add(1).min(self.len());
+
+        self.history[after..]
+            .iter()
+            .position(|record| record.text.contains(query))
+            .map(|index| after + index)
+    }
+
     /// Reset the position.
     #[inline]
     pub fn reset(&mut self) {
+        self.stepped.clear();
         self.position = 0;
     }
 
     #[inline]
-    pub fn get(&self) -> Option<&String> {
+    pub fn get(&self) -> Option<&Record> {
         match self.position.cmp(&0) {
             Ordering::Greater => {
                 // SAFETY: self.position is always valid.
@@ -82,47 +486,58 @@ impl History {
             }
         }
     }
+}
 
-    #[inline]
-    async fn load<P>(data_dir: P) -> io::Result<Self>
-    where
-        P: AsRef<Path>,
-    {
-        let path = history_path(data_dir);
-        let history = fs::read_to_string(path)
-            .await?
-            .lines()
-            .map(String::from)
-            .collect();
-
-        Ok(Self {
-            history,
-            position: 0,
-        })
+/// Keep the newest records within the limits of `settings`, erasing duplicates if asked to.
+#[inline]
+fn limit(mut records: Vec<Record>, settings: &Settings) -> Vec<Record> {
+    if settings.duplicates == Duplicates::Erase {
+        let mut seen = HashSet::new();
+
+        records.reverse();
+        records.retain(|record| seen.insert(record.text.clone()));
+        records.reverse();
     }
 
-    #[inline]
-    pub async fn save<P>(&self, data_dir: P) -> io::Result<()>
-    where
-        P: AsRef<Path>,
-    {
-        let path = history_path(data_dir.as_ref());
-        let history = self.history.join("\n");
-        let _ = fs::create_dir_all(data_dir).await;
+    let max_bytes = settings.max_bytes.saturating_mul(COMPACT_PERCENT) / 100;
+    // the header, and each record, followed by a newline
+    let mut bytes = record::HEADER.len() as u64 + 1;
+    let keep = records
+        .iter()
+        .rev()
+        .take(settings.max_entries)
+        .take_while(|record| {
+            bytes += record.to_string().len() as u64 + 1;
+            bytes <= max_bytes
+        })
+        .count();
+
+    records.drain(..records.len() - keep);
+    records
+}
 
-        fs::write(path, history.as_bytes()).await?;
+/// Returns the name of this machine, empty if it can't be determined.
+#[inline]
+fn hostname() -> String {
+    let mut name = [0_u8; 256];
 
-        Ok(())
+    // SAFETY: `name` is as long as given, and is nul terminated on success.
+    if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } != 0 {
+        return String::new();
     }
+
+    let len = name
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(name.len());
+
+    String::from_utf8_lossy(&name[..len]).into_owned()
 }
 
+/// Returns seconds since the Unix epoch.
 #[inline]
-fn history_path<P>(data_dir: P) -> PathBuf
-where
-    P: AsRef<Path>,
-{
-    let mut path = data_dir.as_ref().to_path_buf();
-
-    path.push("history");
-    path
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
 }
@@ -0,0 +1,297 @@
+//! The history file, shared by every session.
+//!
+//! Sessions append a record as each command finishes, holding a lock on `history.lock` while
+//! reading or writing, so records are never interleaved, or lost to another session saving.
+
+use super::record::{Record, HEADER, MAGIC};
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// Held while the history file is locked against other sessions, released when dropped.
+pub struct Lock(File);
+
+/// Records read from the history file since it was last read.
+pub enum Appended {
+    /// Records following those read before.
+    After(Vec<Record>),
+    /// Every record, as the file was replaced since it was last read.
+    All(Vec<Record>),
+}
+
+#[derive(Debug)]
+pub struct HistoryFile {
+    path: PathBuf,
+    /// Inode, and length of the file as last read, to notice it being appended to, or replaced.
+    read: Option<(u64, u64)>,
+    /// The file is of a newer version, so it's never written, losing what that version added.
+    unknown_version: bool,
+}
+
+impl HistoryFile {
+    #[inline]
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join("history"),
+            read: None,
+            unknown_version: false,
+        }
+    }
+
+    /// Returns the length of the file as last read, or written.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.read.map_or(0, |(_inode, len)| len)
+    }
+
+    /// Wait for other sessions to finish with the file, then lock it.
+    #[inline]
+    pub fn lock(&self) -> io::Result<Lock> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path.with_extension("lock"))?;
+
+        // SAFETY: `file` is open.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Lock(file))
+    }
+
+    /// Read every record, upgrading a file of plain text from an earlier version.
+    ///
+    /// Fails if the file is of a newer version.
+    #[inline]
+    pub fn read_all(&mut self, lock: &Lock) -> io::Result<Vec<Record>> {
+        let contents = self.read_from(0)?;
+
+        self.check_version(&contents)?;
+
+        if contents.is_empty() || contents.starts_with(MAGIC) {
+            return Ok(parse(&contents));
+        }
+
+        let records: Vec<Record> = contents
+            .lines()
+            .map(|line| Record::from_text(line.into()))
+            .collect();
+
+        self.rewrite(lock, &records)?;
+
+        Ok(records)
+    }
+
+    /// Read the records other sessions appended since the file was last read.
+    #[inline]
+    pub fn read_appended(&mut self, _lock: &Lock) -> io::Result<Appended> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.read = None;
+
+                return Ok(Appended::After(Vec::new()));
+            }
+            Err(error) => return Err(error),
+        };
+
+        let (offset, replaced) = match self.read {
+            Some((inode, len)) if inode == metadata.ino() && len <= metadata.len() => (len, false),
+            Some(_read) => (0, true),
+            None => (0, false),
+        };
+
+        let contents = self.read_from(offset)?;
+
+        if offset == 0 {
+            self.check_version(&contents)?;
+        }
+
+        let records = parse(&contents);
+        let appended = if replaced {
+            Appended::All(records)
+        } else {
+            Appended::After(records)
+        };
+
+        Ok(appended)
+    }
+
+    /// Append a record, which must follow everything read.
+    #[inline]
+    pub fn append(&mut self, _lock: &Lock, record: &Record) -> io::Result<()> {
+        self.check_writable()?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+
+        let metadata = file.metadata()?;
+        let mut line = String::new();
+
+        if metadata.len() == 0 {
+            line.push_str(HEADER);
+            line.push('\n');
+        } else {
+            let mut last = [0];
+
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+
+            // a line left incomplete by a session which crashed mustn't swallow this record
+            if last != *b"\n" {
+                line.push('\n');
+            }
+        }
+
+        let _ = writeln!(line, "{record}");
+
+        file.write_all(line.as_bytes())?;
+        self.read = Some((metadata.ino(), metadata.len() + line.len() as u64));
+
+        Ok(())
+    }
+
+    /// Replace the file with `records`.
+    ///
+    /// Written to a temporary file first, so a crash part way through leaves the file as it was.
+    #[inline]
+    pub fn rewrite(&mut self, _lock: &Lock, records: &[Record]) -> io::Result<()> {
+        self.check_writable()?;
+
+        let temporary = self.path.with_extension("tmp");
+        let mut contents = String::from(HEADER);
+
+        contents.push('\n');
+
+        for record in records {
+            let _ = writeln!(contents, "{record}");
+        }
+
+        fs::write(&temporary, contents.as_bytes())?;
+        fs::rename(&temporary, &self.path)?;
+
+        let metadata = fs::metadata(&self.path)?;
+
+        self.read = Some((metadata.ino(), metadata.len()));
+
+        Ok(())
+    }
+
+    /// Fail if `contents`, read from the start of the file, begin with the header of a version
+    /// other than this one, remembering not to write to it.
+    #[inline]
+    fn check_version(&mut self, contents: &str) -> io::Result<()> {
+        let header = contents.lines().next().unwrap_or_default();
+
+        self.unknown_version = header.starts_with(MAGIC) && header != HEADER;
+
+        self.check_writable()
+    }
+
+    /// Fail if the file is of a newer version.
+    #[inline]
+    fn check_writable(&self) -> io::Result<()> {
+        if self.unknown_version {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "history file is of a newer version",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Read the file from `offset` onwards, remembering how much was read.
+    #[inline]
+    fn read_from(&mut self, offset: u64) -> io::Result<String> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.read = None;
+
+                return Ok(String::new());
+            }
+            Err(error) => return Err(error),
+        };
+
+        let metadata = file.metadata()?;
+        let mut bytes = Vec::new();
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_to_end(&mut bytes)?;
+
+        self.read = Some((metadata.ino(), offset + bytes.len() as u64));
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// Parse each record of the current format, skipping the header, and malformed lines.
+#[inline]
+fn parse(contents: &str) -> Vec<Record> {
+    contents
+        .lines()
+        .filter(|line| *line != HEADER)
+        .filter_map(Record::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn upgrades_plain_text() {
+        let dir = env::temp_dir().join(format!("elysh-history-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("history"), "echo a\nls -l\n").unwrap();
+
+        let mut file = HistoryFile::new(&dir);
+        let lock = file.lock().unwrap();
+        let records = file.read_all(&lock).unwrap();
+        let texts: Vec<&str> = records.iter().map(|record| record.text.as_str()).collect();
+
+        assert_eq!(texts, ["echo a", "ls -l"]);
+
+        let contents = fs::read_to_string(dir.join("history")).unwrap();
+
+        assert!(contents.starts_with(HEADER));
+        assert_eq!(file.read_all(&lock).unwrap(), records);
+
+        drop(lock);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_newer_versions() {
+        let dir = env::temp_dir().join(format!("elysh-history-newer-{}", std::process::id()));
+        let contents = "#elysh-history 2\n1\tsomething new\n";
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("history"), contents).unwrap();
+
+        let mut file = HistoryFile::new(&dir);
+        let lock = file.lock().unwrap();
+
+        assert!(file.read_all(&lock).is_err());
+        assert!(file.append(&lock, &Record::from_text("ls".into())).is_err());
+        assert!(file.rewrite(&lock, &[]).is_err());
+        assert_eq!(fs::read_to_string(dir.join("history")).unwrap(), contents);
+
+        drop(lock);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
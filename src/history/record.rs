@@ -0,0 +1,190 @@
+//! History records, and their on-disk format.
+//!
+//! The file begins with `HEADER`, followed by a line per record, each field separated by a tab,
+//! with backslashes, tabs, and newlines escaped. Files without the header are plain lines of
+//! text from earlier versions, read as records with nothing else known.
+
+use core::fmt;
+use core::fmt::Write;
+
+/// The first line of a history file, naming the format version.
+pub const HEADER: &str = "#elysh-history 1";
+
+/// What the header of every version begins with, followed by the version.
+pub const MAGIC: &str = "#elysh-history";
+
+/// A command entered, and what is known of how it ran.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Record {
+    /// The command, including each line of one which spans lines.
+    pub text: String,
+    /// When the command started, in seconds since the Unix epoch.
+    pub start: Option<u64>,
+    /// How long the command ran, in milliseconds.
+    pub duration: Option<u64>,
+    /// Exit code of the command, `None` while it runs.
+    pub status: Option<i32>,
+    /// The directory the command ran within.
+    pub cwd: String,
+    pub hostname: String,
+    /// Identifies the shell which ran the command.
+    pub session: String,
+}
+
+impl Record {
+    /// Construct a record of plain text, from a file of an earlier version.
+    #[inline]
+    pub fn from_text(text: String) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+
+    /// Parse a line of a history file, `None` if it's malformed.
+    #[inline]
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+
+        let start = parse_number(fields.next()?)?;
+        let duration = parse_number(fields.next()?)?;
+        let status = parse_number(fields.next()?)?;
+        let session = unescape(fields.next()?);
+        let hostname = unescape(fields.next()?);
+        let cwd = unescape(fields.next()?);
+        let text = unescape(fields.next()?);
+
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            text,
+            start,
+            duration,
+            status,
+            cwd,
+            hostname,
+            session,
+        })
+    }
+}
+
+/// Writes the record as a line of a history file, without the newline.
+impl fmt::Display for Record {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write_number(fmt, self.start)?;
+        fmt.write_char('\t')?;
+        write_number(fmt, self.duration)?;
+        fmt.write_char('\t')?;
+        write_number(fmt, self.status)?;
+
+        for field in [&self.session, &self.hostname, &self.cwd, &self.text] {
+            fmt.write_char('\t')?;
+            write_escaped(fmt, field)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse an optional number, empty when unknown.
+#[inline]
+fn parse_number<T>(field: &str) -> Option<Option<T>>
+where
+    T: core::str::FromStr,
+{
+    match field {
+        "" => Some(None),
+        field => field.parse().ok().map(Some),
+    }
+}
+
+#[inline]
+fn write_number<T>(fmt: &mut fmt::Formatter, number: Option<T>) -> fmt::Result
+where
+    T: fmt::Display,
+{
+    match number {
+        Some(number) => fmt::Display::fmt(&number, fmt),
+        None => Ok(()),
+    }
+}
+
+#[inline]
+fn write_escaped(fmt: &mut fmt::Formatter, field: &str) -> fmt::Result {
+    for character in field.chars() {
+        match character {
+            '\\' => fmt.write_str("\\\\")?,
+            '\t' => fmt.write_str("\\t")?,
+            '\n' => fmt.write_str("\\n")?,
+            '\r' => fmt.write_str("\\r")?,
+            character => fmt.write_char(character)?,
+        }
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn unescape(field: &str) -> String {
+    let mut string = String::with_capacity(field.len());
+    let mut chars = field.chars();
+
+    while let Some(character) = chars.next() {
+        if character != '\\' {
+            string.push(character);
+
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => string.push('\t'),
+            Some('n') => string.push('\n'),
+            Some('r') => string.push('\r'),
+            Some(character) => string.push(character),
+            None => string.push('\\'),
+        }
+    }
+
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_escapes() {
+        let record = Record {
+            text: String::from("printf 'a\\tb\\n'\techo \\\\\nfor x in y\r"),
+            start: Some(1_700_000_000),
+            duration: Some(12),
+            status: Some(-1),
+            cwd: String::from("/tmp/with\ttab"),
+            hostname: String::from("host"),
+            session: String::from("1-2"),
+        };
+
+        let line = record.to_string();
+
+        assert!(!line.contains('\n'));
+        assert_eq!(line.matches('\t').count(), 6);
+        assert_eq!(Record::parse(&line), Some(record));
+    }
+
+    #[test]
+    fn round_trip_unknown() {
+        let record = Record::from_text(String::from("echo \\"));
+
+        assert_eq!(Record::parse(&record.to_string()), Some(record));
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(Record::parse("echo a"), None);
+        assert_eq!(Record::parse("x\t\t\t\t\t\techo"), None);
+        assert_eq!(Record::parse("\t\t\t\t\t\techo\textra"), None);
+    }
+}
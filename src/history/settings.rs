@@ -0,0 +1,100 @@
+//! What history records, and how much it keeps, from shell variables.
+
+use crate::pattern;
+use regex::Regex;
+
+/// Entries kept without `HISTSIZE`.
+const DEFAULT_ENTRIES: usize = 10_000;
+
+/// Bytes the history file may grow to without `HISTFILEBYTES`.
+const DEFAULT_BYTES: u64 = 2 * 1024 * 1024;
+
+/// How repeats of a command are recorded, from `HISTCONTROL`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Duplicates {
+    /// Record every command.
+    Keep,
+    /// Skip a command the same as the one before it, `ignoredups`.
+    IgnoreConsecutive,
+    /// Remove earlier records of a command when it's recorded again, `erasedups`.
+    Erase,
+}
+
+#[derive(Debug)]
+pub struct Settings {
+    /// Entries kept in memory, and within the file, `HISTSIZE`.
+    pub max_entries: usize,
+    /// Size the file may grow to before it's compacted, to a little under, `HISTFILEBYTES`.
+    pub max_bytes: u64,
+    pub duplicates: Duplicates,
+    /// Patterns of commands never recorded, separated by `:` within `HISTIGNORE`, i.e.
+    /// `*TOKEN=*`.
+    pub ignore: Vec<String>,
+    /// A regular expression matching commands never recorded, `HISTIGNORE_REGEX`, as `:` is
+    /// common within one.
+    pub ignore_regex: Option<Regex>,
+    /// Keep commands other sessions run, `set -o sharehistory`.
+    pub share: bool,
+}
+
+impl Settings {
+    /// Read the settings from shell variables, along with `ignore_regex`, compiled from
+    /// `HISTIGNORE_REGEX` by the caller so it isn't parsed again each time.
+    #[inline]
+    pub fn from_vars<F>(var: F, ignore_regex: Option<Regex>, share: bool) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let max_entries = var("HISTSIZE")
+            .and_then(|entries| entries.parse().ok())
+            .unwrap_or(DEFAULT_ENTRIES);
+
+        let max_bytes = var("HISTFILEBYTES")
+            .and_then(|bytes| bytes.parse().ok())
+            .unwrap_or(DEFAULT_BYTES);
+
+        let control = var("HISTCONTROL").unwrap_or_default();
+        let has_control = |name| control.split(':').any(|control| control == name);
+        let duplicates = if has_control("erasedups") {
+            Duplicates::Erase
+        } else if has_control("ignoredups") {
+            Duplicates::IgnoreConsecutive
+        } else {
+            Duplicates::Keep
+        };
+
+        let ignore = var("HISTIGNORE")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|pattern| !pattern.is_empty())
+            .map(String::from)
+            .collect();
+
+        Self {
+            max_entries,
+            max_bytes,
+            duplicates,
+            ignore,
+            ignore_regex,
+            share,
+        }
+    }
+
+    /// Is `text` never recorded?
+    ///
+    /// Blank commands, and those beginning with a space, are never recorded, so a command can be
+    /// kept private by typing a space first.
+    #[inline]
+    pub fn ignores(&self, text: &str) -> bool {
+        text.trim().is_empty()
+            || text.starts_with(char::is_whitespace)
+            || self
+                .ignore
+                .iter()
+                .any(|pattern| pattern::matches(pattern, text))
+            || self
+                .ignore_regex
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(text))
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use termios::Termios;
+
+/// A process belonging to a job.
+#[derive(Debug)]
+pub struct Process {
+    pub pid: libc::pid_t,
+    /// The last status reported by `waitpid`, `None` while running.
+    pub status: Option<ExitStatus>,
+}
+
+impl Process {
+    #[inline]
+    pub const fn new(pid: libc::pid_t) -> Self {
+        Self { pid, status: None }
+    }
+
+    /// Has this process exited, or been killed?
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        match self.status {
+            Some(status) => status.stopped_signal().is_none(),
+            None => false,
+        }
+    }
+
+    /// Is this process stopped?
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        match self.status {
+            Some(status) => status.stopped_signal().is_some(),
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum State {
+    Running,
+    Stopped,
+    Done,
+}
+
+impl State {
+    #[inline]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            State::Running => "Running",
+            State::Stopped => "Stopped",
+            State::Done => "Done",
+        }
+    }
+}
+
+/// A pipeline running in its own process group.
+#[derive(Debug)]
+pub struct Job {
+    pub id: usize,
+    pub pgid: libc::pid_t,
+    pub processes: Vec<Process>,
+    /// The command line which started the job.
+    pub command: String,
+    /// Terminal modes of the job when it was stopped, restored when it is resumed.
+    pub termios: Option<Termios>,
+    /// Status overriding that of the last process, i.e. when it failed to spawn.
+    pub status: Option<ExitStatus>,
+}
+
+impl Job {
+    #[inline]
+    pub fn state(&self) -> State {
+        if self.processes.iter().all(Process::is_done) {
+            State::Done
+        } else if self
+            .processes
+            .iter()
+            .all(|process| process.is_done() || process.is_stopped())
+        {
+            State::Stopped
+        } else {
+            State::Running
+        }
+    }
+
+    /// Returns the status of the job, the status of the last process.
+    #[inline]
+    pub fn status(&self) -> Option<ExitStatus> {
+        self.status
+            .or_else(|| self.processes.last().and_then(|process| process.status))
+    }
+
+    /// Mark every stopped process as running again, after `SIGCONT`.
+    #[inline]
+    pub fn continued(&mut self) {
+        for process in &mut self.processes {
+            if process.is_stopped() {
+                process.status = None;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Job {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let state = match (self.state(), self.status().and_then(|status| status.code())) {
+            (State::Done, Some(code)) if code != 0 => format!("Exit {code}"),
+            (state, _) => state.as_str().into(),
+        };
+
+        write!(fmt, "[{}]  {:<8} {}", self.id, state, self.command)
+    }
+}
+
+/// The job table.
+#[derive(Debug, Default)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+}
+
+impl Jobs {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { jobs: Vec::new() }
+    }
+
+    /// Add a job to the table, returning its id, the lowest one not in use.
+    #[inline]
+    pub fn insert(
+        &mut self,
+        pgid: libc::pid_t,
+        processes: Vec<Process>,
+        command: String,
+        status: Option<ExitStatus>,
+    ) -> usize {
+        let id = (1..)
+            .find(|id| self.jobs.iter().all(|job| job.id != *id))
+            .unwrap_or_default();
+
+        self.jobs.push(Job {
+            id,
+            pgid,
+            processes,
+            command,
+            termios: None,
+            status,
+        });
+
+        id
+    }
+
+    #[inline]
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Returns the id of the current job, the one most recently started or stopped.
+    #[inline]
+    pub fn current(&self) -> Option<usize> {
+        self.jobs.last().map(|job| job.id)
+    }
+
+    /// Move a job to the end of the table, making it the current job.
+    #[inline]
+    pub fn make_current(&mut self, id: usize) {
+        if let Some(index) = self.jobs.iter().position(|job| job.id == id) {
+            let job = self.jobs.remove(index);
+
+            self.jobs.push(job);
+        }
+    }
+
+    #[inline]
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+
+        Some(self.jobs.remove(index))
+    }
+
+    /// Record the status `waitpid` reported for `pid`.
+    #[inline]
+    pub fn update(&mut self, pid: libc::pid_t, status: ExitStatus) {
+        let process = self
+            .jobs
+            .iter_mut()
+            .flat_map(|job| job.processes.iter_mut())
+            .find(|process| process.pid == pid);
+
+        if let Some(process) = process {
+            process.status = if status.continued() {
+                None
+            } else {
+                Some(status)
+            };
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter()
+    }
+
+    /// Parse a job specifier, `%n`, `n`, or `%%`/`%+`/nothing for the current job.
+    #[inline]
+    pub fn find(&self, spec: Option<&str>) -> Option<usize> {
+        match spec {
+            None | Some("%%" | "%+" | "%") => self.current(),
+            Some(spec) => {
+                let spec = spec.strip_prefix('%').unwrap_or(spec);
+                let id = spec.parse().ok()?;
+
+                self.get(id).map(|job| job.id)
+            }
+        }
+    }
+}
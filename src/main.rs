@@ -8,23 +8,43 @@
 #![feature(str_split_whitespace_as_str)]
 #![feature(type_name_of_val)]
 
-use context::{Context, Prompt};
+use context::{exit_status, status_code, Context, Prompt, Stage};
+use history::Search;
 use input::Input;
 use paths::Summary;
-use std::io;
-use std::io::ErrorKind;
-use std::path::PathBuf;
+use std::future::Future;
+use std::io::Read;
+use std::mem;
+use std::ops::ControlFlow;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::pin::{pin, Pin};
+use std::process::ExitStatus;
+use std::task::{self, Poll, Waker};
+use std::{env, fs, io, process};
 use tokio::fs::OpenOptions;
 
 mod context;
+mod expand;
+mod glob;
 mod history;
 mod input;
+mod jobs;
+mod options;
 mod paths;
+mod pattern;
 mod session;
+mod variables;
+
+/// How many files may be sourced, one within another, before `source` fails.
+const MAX_SOURCE_DEPTH: usize = 64;
 
 const WORD_CHARS: &[char] = &['/', '[', '&', '.', ';', '!', ']', '}', ':', '"', '|', ' '];
 
-use elysh_syntax::Var;
+use elysh_syntax::{
+    is_name, Case, Command, Element, For, If, List, Operator, Piped, Pipeline, Redirect, Script,
+    ScriptError, Statement, Value, Var, While,
+};
 use elysh_theme::{Color, DisplaySpaced, Style};
 use std::fmt;
 use std::fmt::Write;
@@ -58,6 +78,41 @@ impl<'a> fmt::Display for DisplayArg<'a> {
     }
 }
 
+/// A value, with each quoted string within it styled.
+pub struct DisplayValue<'a> {
+    string_style: &'a Style,
+    value: Value<'a>,
+}
+
+impl<'a> DisplayValue<'a> {
+    pub fn new(string_style: &'a Style, value: Value<'a>) -> Self {
+        Self {
+            string_style,
+            value,
+        }
+    }
+}
+
+impl<'a> fmt::Display for DisplayValue<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for segment in self.value.segments() {
+            match segment.quote() {
+                Some(quote) => {
+                    let arg =
+                        DisplayArg::new(segment.is_incomplete(), quote.as_char(), segment.as_str());
+
+                    fmt.write_str(self.string_style.as_ansi())?;
+                    fmt::Display::fmt(&arg, fmt)?;
+                    fmt.write_str("\x1b[m")?;
+                }
+                None => fmt.write_str(segment.as_str())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub struct DisplayVar<'a> {
     seperator_style: &'a Style,
     string_style: &'a Style,
@@ -89,18 +144,8 @@ impl<'a> fmt::Display for DisplayVar<'a> {
                     .finish()?;
 
                 let mut spaced = DisplaySpaced::new(fmt);
-                if let Some(quote) = val.quote() {
-                    spaced
-                        .style(&self.string_style)
-                        .entry(&DisplayArg::new(
-                            val.is_incomplete(),
-                            quote.as_char(),
-                            val.as_str(),
-                        ))
-                        .clear_style();
-                } else {
-                    spaced.entry(&val.as_str());
-                }
+
+                spaced.entry(&DisplayValue::new(self.string_style, *val));
             }
             Var::IncompletePair(key) => {
                 fmt.write_str(&key)?;
@@ -112,27 +157,227 @@ impl<'a> fmt::Display for DisplayVar<'a> {
     }
 }
 
+pub struct DisplayRedirect<'a> {
+    redirect_style: &'a Style,
+    string_style: &'a Style,
+    redirect: &'a Redirect<'a>,
+}
+
+impl<'a> DisplayRedirect<'a> {
+    pub fn new(
+        redirect_style: &'a Style,
+        string_style: &'a Style,
+        redirect: &'a Redirect<'a>,
+    ) -> Self {
+        Self {
+            redirect_style,
+            string_style,
+            redirect,
+        }
+    }
+}
+
+impl<'a> fmt::Display for DisplayRedirect<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let redirect = self.redirect;
+
+        if let Some(fd) = redirect.fd {
+            fmt::Display::fmt(&fd, fmt)?;
+        }
+
+        fmt.write_str(self.redirect_style.as_ansi())?;
+        fmt.write_str(redirect.operator.as_str())?;
+        fmt.write_str("\x1b[m")?;
+        fmt.write_str(redirect.whitespace)?;
+
+        match &redirect.target {
+            Some(target) => {
+                fmt::Display::fmt(&DisplayValue::new(self.string_style, *target), fmt)?;
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+pub struct DisplayCommand<'a> {
+    command: &'a elysh_syntax::Command<'a>,
+    summary: &'a Summary,
+}
+
+impl<'a> DisplayCommand<'a> {
+    pub fn new(command: &'a elysh_syntax::Command<'a>, summary: &'a Summary) -> Self {
+        Self { command, summary }
+    }
+}
+
+impl<'a> fmt::Display for DisplayCommand<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let command = self.command;
+        let seperator_style = Style::new(Color::Blue);
+        let string_style = Style::new(Color::Green);
+        let exact_style = Style::new(Color::Green);
+        let partial_style = Style::new(Color::Black).bright(true);
+        let redirect_style = Style::new(Color::Yellow);
+
+        let mut spaced = DisplaySpaced::new(fmt);
+
+        for env in &command.vars {
+            spaced.entry(&DisplayVar::new(&seperator_style, &string_style, &env));
+        }
+
+        spaced.finish()?;
+
+        if !command.vars.is_empty() && !command.program.as_str().is_empty() {
+            fmt.write_char(' ')?;
+        }
+
+        if let Some(display) = self.summary.display(&exact_style, &partial_style) {
+            fmt::Display::fmt(&display, fmt)?;
+        } else {
+            let arg = &command.program;
+            let mut spaced = DisplaySpaced::new(fmt);
+
+            match arg.value() {
+                Some(value) => spaced.entry(&DisplayValue::new(&string_style, value)),
+                None => spaced.entry(&arg.as_str()),
+            };
+
+            spaced.finish()?;
+        }
+
+        if !command.args.is_empty() {
+            fmt.write_char(' ')?;
+        }
+
+        let mut spaced = DisplaySpaced::new(fmt);
+
+        for arg in &command.args {
+            match arg.value() {
+                Some(value) => spaced.entry(&DisplayValue::new(&string_style, value)),
+                None => spaced.entry(&arg.as_str()),
+            };
+        }
+
+        spaced.finish()?;
+
+        let is_empty = command.vars.is_empty()
+            && command.program.as_str().is_empty()
+            && command.args.is_empty();
+
+        if !is_empty && !command.redirects.is_empty() {
+            fmt.write_char(' ')?;
+        }
+
+        let mut spaced = DisplaySpaced::new(fmt);
+
+        for redirect in &command.redirects {
+            spaced.entry(&DisplayRedirect::new(
+                &redirect_style,
+                &string_style,
+                redirect,
+            ));
+        }
+
+        spaced.finish()
+    }
+}
+
+pub struct DisplayOperator<'a> {
+    operator_style: &'a Style,
+    /// The operator, including surrounding whitespace.
+    string: &'a str,
+}
+
+impl<'a> DisplayOperator<'a> {
+    pub fn new(operator_style: &'a Style, string: &'a str) -> Self {
+        Self {
+            operator_style,
+            string,
+        }
+    }
+}
+
+impl<'a> fmt::Display for DisplayOperator<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let string = self.string;
+        let (start, end) = string.split_at(string.len() - string.trim_start().len());
+        let (operator, end) = end.split_at(end.trim_end().len());
+
+        fmt.write_str(start)?;
+        fmt.write_str(self.operator_style.as_ansi())?;
+        fmt.write_str(operator)?;
+        fmt.write_str("\x1b[m")?;
+        fmt.write_str(end)?;
+
+        Ok(())
+    }
+}
+
 pub struct Display<'a> {
     prompt: &'a Prompt,
-    command: Result<elysh_syntax::Command<'a>, elysh_syntax::CommandError<'a>>,
+    list: Result<List<'a>, elysh_syntax::CommandError<'a>>,
+    /// Summaries of each command but the last, which only ever match exactly.
+    exact: Vec<Summary>,
     string: &'a str,
     shift: usize,
     summary: &'a Summary,
+    /// How many paths the glob being typed matches.
+    matches: Option<String>,
+    /// Is the line a continuation, shown as-is?
+    continuation: bool,
+    /// A comment ending the line.
+    comment: &'a str,
 }
 
 impl<'a> Display<'a> {
     pub fn new(context: &'a Context, summary: &'a Summary) -> Self {
         let prompt = &context.prompt;
-        let command = context.command();
-        let string = &context.edit;
-        let shift = context.edit.shift() + summary.shift();
+        let list = context.list();
+        let (string, comment) = context.edit.split_comment();
+        let continuation = context.is_continuation();
+        let matches = match &list {
+            Ok(list) if !continuation => glob_matches(context, list),
+            _ => None,
+        };
+
+        let shift = context.edit.shift()
+            + summary.shift()
+            + matches.as_ref().map(|matches| matches.len()).unwrap_or(0);
+
+        let exact = match &list {
+            Ok(list) => {
+                let commands = list
+                    .pipelines
+                    .iter()
+                    .flat_map(|pipeline| pipeline.commands.iter());
+
+                let len = commands.clone().count();
+
+                commands
+                    .take(len.saturating_sub(1))
+                    .map(
+                        |command| match context.search_program(command.program.as_str()) {
+                            Summary::Exact(exact) => Summary::Exact(exact),
+                            _ => Summary::NoMatch,
+                        },
+                    )
+                    .collect()
+            }
+            Err(_error) => Vec::new(),
+        };
 
         Self {
             prompt,
-            command,
+            list,
+            exact,
             string,
             shift,
             summary,
+            matches,
+            continuation,
+            comment,
         }
     }
 }
@@ -143,75 +388,50 @@ impl<'a> fmt::Display for Display<'a> {
 
         fmt::Display::fmt(&self.prompt, fmt)?;
 
-        let seperator_style = Style::new(Color::Blue);
-        let string_style = Style::new(Color::Green);
-        let exact_style = Style::new(Color::Green);
-        let partial_style = Style::new(Color::Black).bright(true);
-
-        match &self.command {
-            Ok(command) => {
-                let mut spaced = DisplaySpaced::new(fmt);
+        let operator_style = Style::new(Color::Cyan);
 
-                for env in &command.vars {
-                    spaced.entry(&DisplayVar::new(&seperator_style, &string_style, &env));
-                }
+        match &self.list {
+            _ if self.continuation => {
+                fmt.write_str(self.string)?;
+                fmt.write_str(self.comment)?;
+            }
+            Ok(list) => {
+                let mut summaries = self.exact.iter().chain(Some(self.summary));
 
-                spaced.finish()?;
+                for (index, pipeline) in list.pipelines.iter().enumerate() {
+                    for (index, command) in pipeline.commands.iter().enumerate() {
+                        let summary = summaries.next().unwrap_or(&Summary::NoMatch);
 
-                if !command.vars.is_empty() && !command.program.as_str().is_empty() {
-                    fmt.write_char(' ')?;
-                }
+                        fmt::Display::fmt(&DisplayCommand::new(command, summary), fmt)?;
 
-                if let Some(display) = self.summary.display(&exact_style, &partial_style) {
-                    fmt::Display::fmt(&display, fmt)?;
-                } else {
-                    let arg = &command.program;
-                    let mut spaced = DisplaySpaced::new(fmt);
-
-                    if let Some(quote) = arg.quote() {
-                        spaced
-                            .style(&string_style)
-                            .entry(&DisplayArg::new(
-                                arg.is_incomplete(),
-                                quote.as_char(),
-                                arg.as_str(),
-                            ))
-                            .clear_style();
-                    } else {
-                        spaced.entry(&arg.as_str());
+                        if let Some(pipe) = pipeline.pipes.get(index) {
+                            fmt::Display::fmt(&DisplayOperator::new(&operator_style, pipe), fmt)?;
+                        }
                     }
 
-                    spaced.finish()?;
+                    if let Some((_operator, separator)) = list.separators.get(index) {
+                        fmt::Display::fmt(&DisplayOperator::new(&operator_style, separator), fmt)?;
+                    }
                 }
 
-                if !command.args.is_empty() {
+                if self.string.ends_with(char::is_whitespace) && !renders_trailing_whitespace(list)
+                {
                     fmt.write_char(' ')?;
                 }
 
-                let mut spaced = DisplaySpaced::new(fmt);
-
-                for arg in &command.args {
-                    if let Some(quote) = arg.quote() {
-                        spaced
-                            .style(&string_style)
-                            .entry(&DisplayArg::new(
-                                arg.is_incomplete(),
-                                quote.as_char(),
-                                arg.as_str(),
-                            ))
-                            .clear_style();
-                    } else {
-                        spaced.entry(&arg.as_str());
-                    }
+                if !self.comment.is_empty() {
+                    fmt.write_str("\x1b[38;5;8m")?;
+                    fmt.write_str(self.comment)?;
+                    fmt.write_str("\x1b[m")?;
                 }
-
-                spaced.finish()?;
             }
             _ => {}
         }
 
-        if self.string.ends_with(char::is_whitespace) {
-            fmt.write_char(' ')?;
+        if let Some(matches) = &self.matches {
+            fmt.write_str("\x1b[38;5;8m")?;
+            fmt.write_str(matches)?;
+            fmt.write_str("\x1b[m")?;
         }
 
         match self.shift {
@@ -228,163 +448,1923 @@ impl<'a> fmt::Display for Display<'a> {
     }
 }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> io::Result<()> {
-    let tty = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("/dev/tty")
-        .await?;
+/// The line shown while searching history, the query followed by the item matching, with the
+/// match highlighted.
+pub struct DisplaySearch<'a> {
+    search: &'a Search,
+    /// The item matching, with each newline shown as `↵`.
+    item: Option<String>,
+}
 
-    let mut context = Context::new(tty).await?;
+impl<'a> DisplaySearch<'a> {
+    pub fn new(context: &'a Context, search: &'a Search) -> Self {
+        let item = search
+            .found()
+            .map(|_index| context.edit.as_str().replace('\n', " ↵ "));
+
+        Self { search, item }
+    }
+}
+
+impl<'a> fmt::Display for DisplaySearch<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("\r\x1b[K ")?;
+
+        if self.search.failed() {
+            fmt.write_str("\x1b[38;5;1m(search)\x1b[m ")?;
+        } else {
+            fmt.write_str("\x1b[38;5;8m(search)\x1b[m ")?;
+        }
+
+        let query = self.search.query();
+
+        fmt.write_str(query)?;
+
+        let Some(item) = &self.item else {
+            return Ok(());
+        };
+
+        fmt.write_str("\x1b[38;5;8m │ \x1b[m")?;
+
+        match item.find(query).filter(|_index| !query.is_empty()) {
+            Some(index) => {
+                let (start, rest) = item.split_at(index);
+                let (found, end) = rest.split_at(query.len());
+
+                fmt.write_str(start)?;
+                fmt.write_str("\x1b[7m")?;
+                fmt.write_str(found)?;
+                fmt.write_str("\x1b[m")?;
+                fmt.write_str(end)?;
+            }
+            None => fmt.write_str(item)?,
+        }
+
+        // leave the cursor after the query
+        let shift = 3 + item.chars().count();
+
+        fmt.write_str("\x1b[")?;
+        fmt::Display::fmt(&shift, fmt)?;
+        fmt.write_char('D')?;
+
+        Ok(())
+    }
+}
+
+/// Render the line being edited, or the search through history under way.
+fn render(context: &Context) -> String {
+    match context.search() {
+        Some(search) => DisplaySearch::new(context, search).to_string(),
+        None => {
+            let summary = context.suggest();
+
+            Display::new(context, &summary).to_string()
+        }
+    }
+}
+
+/// Handle input while searching history, returning whether it was handled.
+///
+/// Anything else accepts the match, then is handled as usual, so Enter runs it.
+fn search_input(context: &mut Context, input: &Input) -> bool {
+    match input {
+        Input::Key('r') if input.ctrl() => context.search_older(),
+        Input::Key('s') if input.ctrl() => context.search_newer(),
+        // escape, or Ctrl-G
+        Input::Key('[' | 'g') if input.ctrl() => context.cancel_search(),
+        Input::Backspace if input.none() => context.search_remove(),
+        Input::Space if input.none() => context.search_insert(' '),
+        Input::Key(key) if input.none() => context.search_insert(*key),
+        Input::Paste(string) if input.none() => {
+            for character in string.chars().filter(|character| *character != '\n') {
+                context.search_insert(character);
+            }
+        }
+        _ => {
+            context.accept_search();
+
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Describe how many paths the last argument matches, when it is an unquoted glob.
+///
+/// Globs containing `**`, or expansions, aren't walked on every keystroke.
+fn glob_matches(context: &Context, list: &List<'_>) -> Option<String> {
+    let value = list.last().last().args.last()?.value()?;
+    let pattern = value.as_str();
+
+    if value.is_quoted()
+        || !pattern::is_pattern(pattern)
+        || pattern.contains("**")
+        || pattern.contains('$')
+    {
+        return None;
+    }
+
+    let len: usize = value
+        .braces()
+        .ok()?
+        .iter()
+        .map(|pattern| glob::expand(&expand::tilde(context, pattern)).len())
+        .sum();
+
+    let matches = match len {
+        1 => String::from(" (1 match)"),
+        len => format!(" ({len} matches)"),
+    };
+
+    Some(matches)
+}
+
+/// Whether trailing whitespace is already rendered as part of an operator, redirection, or a
+/// quote left open.
+fn renders_trailing_whitespace(list: &List<'_>) -> bool {
+    let pipeline = list.last();
+    let last = pipeline.last();
+
+    if let Some(redirect) = last.redirects.last() {
+        return redirect.target.is_none_or(|target| target.is_incomplete());
+    }
+
+    if last.args.last().unwrap_or(&last.program).is_incomplete() {
+        return true;
+    }
+
+    last.is_empty() && (pipeline.commands.len() > 1 || list.pipelines.len() > 1)
+}
+
+/// Move below the input line before anything is written, only once.
+async fn begin_output(context: &Context, output: &mut bool) -> io::Result<()> {
+    if !mem::replace(output, true) {
+        context.write_all(b"\r\n").await?;
+    }
+
+    Ok(())
+}
+
+/// Why running commands stopped early.
+enum Jump {
+    /// `break n`, leaving `n` loops.
+    Break(usize),
+    /// `continue n`, continuing the `n`th loop out.
+    Continue(usize),
+    /// A command was interrupted by Ctrl-C, which stops everything.
+    Interrupt,
+    /// `return n`, leaving the running function.
+    Return(ExitStatus),
+    /// `exit`.
+    Exit,
+}
+
+/// The result of running commands, which breaks when they stopped early.
+type Flow = io::Result<ControlFlow<Jump>>;
+
+/// Run a script, then report jobs which finished in the background before the next prompt.
+///
+/// `output` is whether the cursor is already below the input. Breaks if the shell should exit.
+async fn run_script(
+    context: &mut Context,
+    script: &Script<'_>,
+    mut output: bool,
+) -> io::Result<ControlFlow<()>> {
+    if let ControlFlow::Break(Jump::Exit) = run_statements(context, script, &mut output).await? {
+        return Ok(ControlFlow::Break(()));
+    }
+
+    let notices = context.finished_jobs();
+
+    if !notices.is_empty() {
+        begin_output(context, &mut output).await?;
+        context.write_all(notices.as_bytes()).await?;
+    }
+
+    // nothing was written, redraw the prompt in place
+    if !output {
+        context.write_all(b"\x1b[2A").await?;
+    }
 
-    context.enable_raw().await?;
     context.pre_prompt().await?;
 
-    loop {
-        let summary = context.suggest();
-        let display = Display::new(&context, &summary).to_string();
-
-        context.session.write_all(display.as_bytes()).await?;
-
-        let input = context.next_input().await?;
-
-        match input {
-            Input::ArrowUp if input.none() => context.history_up(),
-            Input::Key('p') if input.ctrl() => context.history_up(),
-            Input::ArrowDown if input.none() => context.history_down(),
-            Input::Key('n') if input.ctrl() => context.history_down(),
-            /*Input::ArrowRight if input.none() => {
-                if let Summary::Partial(partial, rest) = &summary {
-                    if context.edit.at_end() {
-                        context.edit = format!("{partial}{rest}").into();
-                        context.edit.to_end();
-                    }
-                }
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Run each statement of a script, stopping early if one does.
+fn run_statements<'a>(
+    context: &'a mut Context,
+    script: &'a Script<'a>,
+    output: &'a mut bool,
+) -> Pin<Box<dyn Future<Output = Flow> + 'a>> {
+    Box::pin(async move {
+        for statement in &script.statements {
+            let flow = run_statement(context, statement, output).await?;
 
-                context.edit.right(1);
+            if flow.is_break() {
+                return Ok(flow);
             }
-            Input::Key('i') if input.ctrl() => {
-                if let Summary::Partial(partial, rest) = &summary {
-                    if context.edit.at_end() {
-                        context.edit = format!("{partial}{rest}").into();
-                        context.edit.to_end();
-                    }
-                }
-                context.edit.right(1);
-            }*/
-            Input::ArrowLeft if input.none() => context.prev(),
-            Input::ArrowRight if input.none() => context.next(),
-            Input::Key('c') if input.ctrl() => context.clear(),
-            Input::Key('d') if input.ctrl() => break,
-            Input::Key('m') if input.ctrl() => {
-                if !context.edit.is_empty() {
-                    context.execute_edit = true;
-                }
-            }
-            Input::Key('w') if input.ctrl() => context.remove_word(WORD_CHARS),
-            Input::Key('k') if input.ctrl() => context.remove_end(),
-
-            Input::ArrowLeft if input.ctrl() => context.prev_word(WORD_CHARS),
-            Input::ArrowLeft if input.shift() => context.prev_word(WORD_CHARS),
-            Input::Key('b') if input.meta() => context.prev_word(WORD_CHARS),
-
-            Input::ArrowRight if input.ctrl() => context.next_word(WORD_CHARS),
-            Input::ArrowRight if input.shift() => context.next_word(WORD_CHARS),
-            Input::Key('f') if input.meta() => context.next_word(WORD_CHARS),
-
-            Input::Backspace if input.none() => context.remove(),
-            Input::Space if input.none() => context.insert(' '),
-            Input::Home if input.none() => context.to_start(),
-            Input::Key('a') if input.ctrl() => context.to_start(),
-            Input::End if input.none() => context.to_end(),
-            Input::Key('e') if input.ctrl() => context.to_end(),
-            Input::Key(key) if input.none() => context.insert(key),
-            Input::Paste(string) if input.none() => context.insert_str(&string),
-            _ => {}
         }
 
-        let command = context
-            .should_execute()
-            .and_then(|_| context.command().ok());
+        Ok(ControlFlow::Continue(()))
+    })
+}
 
-        if let Some(command) = command {
-            match command.program.as_str() {
-                "exit" => {
-                    break;
-                }
-                "cd" => {
-                    let target_dir = command
-                        .args
-                        .get(0)
-                        .map(|arg| arg.as_str())
-                        .map(PathBuf::from)
-                        .unwrap_or_else(|| context.home_dir.clone());
+/// Run a single statement.
+fn run_statement<'a>(
+    context: &'a mut Context,
+    statement: &'a Statement<'a>,
+    output: &'a mut bool,
+) -> Pin<Box<dyn Future<Output = Flow> + 'a>> {
+    Box::pin(async move {
+        let flow = match statement {
+            Statement::List(list) => run_list(context, list, Operator::Semicolon, output).await?,
+            Statement::If(clause) => run_if(context, clause, output).await?,
+            Statement::While(clause) => {
+                context.loops += 1;
 
-                    let result = context.change_dir(&target_dir);
+                let flow = run_while(context, clause, output).await;
 
-                    match result {
-                        Err(error) if error.kind() == ErrorKind::NotFound => {
-                            let edit = format!(
-                                "\relysh: `{}` no such file or directory\r\n",
-                                target_dir.display()
-                            );
+                context.loops -= 1;
+                flow?
+            }
+            Statement::For(clause) => {
+                context.loops += 1;
 
-                            context.session.write_all(edit.as_bytes()).await?;
-                        }
-                        _ => {}
-                    }
+                let flow = run_for(context, clause, output).await;
 
-                    context.session.write_all(b"\x1b[2A").await?;
-                    context.pre_prompt().await?;
-                }
-                "showkeys" => {
-                    context.toggle_showkeys();
+                context.loops -= 1;
+                flow?
+            }
+            Statement::Case(clause) => run_case(context, clause, output).await?,
+            Statement::Pipeline(piped) => run_piped(context, piped, output).await?,
+            Statement::Function(function) => {
+                context.define_function(function.name, function.source);
+                context.set_status(exit_status(0));
+
+                ControlFlow::Continue(())
+            }
+            Statement::AndOr(operator, statement) => match &**statement {
+                Statement::List(list) => run_list(context, list, *operator, output).await?,
+                statement if runs_after(context, *operator) => {
+                    run_statement(context, statement, output).await?
                 }
-                _ => {
-                    let target_dir = PathBuf::from(command.program.as_str());
+                _statement => ControlFlow::Continue(()),
+            },
+        };
 
-                    context.session.write_all(b"\r\n").await?;
+        Ok(flow)
+    })
+}
 
-                    let result = match context.spawn(&command).await {
-                        Ok(ok) => Ok(ok),
-                        Err(_error) => {
-                            let target_dir = context.expand_path(&target_dir);
+/// Does a command following `separator` run, given the previous exit status?
+fn runs_after(context: &Context, separator: Operator) -> bool {
+    match separator {
+        Operator::And => context.status().success(),
+        Operator::Or => !context.status().success(),
+        _ => true,
+    }
+}
 
-                            match context.change_dir(&target_dir) {
-                                Ok(()) => {
-                                    context.session.write_all(b"\x1b[3A").await?;
+/// Run each pipeline of a list, skipping those after `&&` or `||` depending on the previous
+/// exit status, which is recorded as `$?`, and not waiting for those followed by `&`.
+///
+/// `separator` is the operator before the first pipeline, `&&` or `||` when the list follows a
+/// compound command, otherwise `;`.
+///
+/// Breaks if a builtin stops the script, or a pipeline is interrupted.
+async fn run_list(
+    context: &mut Context,
+    list: &List<'_>,
+    mut separator: Operator,
+    output: &mut bool,
+) -> Flow {
+    for (index, pipeline) in list.pipelines.iter().enumerate() {
+        let run = runs_after(context, separator);
 
-                                    Ok(Ok(()))
-                                },
-                                Err(error) => Err(error),
-                            }
-                        }
-                    };
+        if run && !pipeline.is_empty() {
+            let background = list.is_background(index);
 
-                    match result {
-                        Err(error) if error.kind() == ErrorKind::NotFound => {
-                            let edit = format!(
-                                "\relysh: `{}` no such file or directory\r\n",
-                                target_dir.display()
-                            );
+            match run_pipeline(context, pipeline, background, output).await? {
+                ControlFlow::Continue(status) => {
+                    context.set_status(status);
 
-                            context.session.write_all(edit.as_bytes()).await?;
-                        }
-                        _ => {}
+                    if status.signal() == Some(libc::SIGINT) {
+                        return Ok(ControlFlow::Break(Jump::Interrupt));
                     }
-
-                    context.pre_prompt().await?;
                 }
+                ControlFlow::Break(jump) => return Ok(ControlFlow::Break(jump)),
             }
+        }
+
+        if let Some((operator, _string)) = list.separators.get(index) {
+            separator = *operator;
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Run a command list in a subshell, for command substitution, returning the status to exit
+/// with.
+fn run_subshell(context: &mut Context, list: &List<'_>) -> ExitStatus {
+    // there is no input line to move below
+    let mut output = true;
+
+    let flow = block_on(run_list(context, list, Operator::Semicolon, &mut output));
+
+    subshell_status(context, flow)
+}
+
+/// Returns the status a subshell exits with, once it ran `flow`.
+fn subshell_status(context: &Context, flow: Flow) -> ExitStatus {
+    match flow {
+        Ok(ControlFlow::Break(Jump::Return(status))) => status,
+        Ok(_flow) => context.status(),
+        Err(_error) => exit_status(1),
+    }
+}
+
+/// Might `source` run command substitution, whose subshell reports its own errors, so output
+/// must begin before it's expanded?
+fn substitutes(context: &Context, source: &str) -> bool {
+    source.contains("$(") || (context.options.backticks && source.contains('`'))
+}
+
+/// Run a future to completion in a subshell, which left the runtime behind in the parent.
+///
+/// Without a terminal, nothing the shell runs waits on the runtime, so it's polled until ready.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = task::Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Run the body of the first branch whose condition succeeds, otherwise the `else` body.
+async fn run_if(context: &mut Context, clause: &If<'_>, output: &mut bool) -> Flow {
+    for (condition, body) in &clause.branches {
+        let flow = run_statements(context, condition, output).await?;
+
+        if flow.is_break() {
+            return Ok(flow);
+        }
+
+        if context.status().success() {
+            return run_statements(context, body, output).await;
+        }
+    }
+
+    match &clause.otherwise {
+        Some(body) => run_statements(context, body, output).await,
+        None => {
+            context.set_status(exit_status(0));
+
+            Ok(ControlFlow::Continue(()))
+        }
+    }
+}
 
-            context.clear_and_record();
+/// Run the body while the condition succeeds, or until it does.
+///
+/// The status is that of the last body run.
+async fn run_while(context: &mut Context, clause: &While<'_>, output: &mut bool) -> Flow {
+    let mut status = exit_status(0);
+
+    loop {
+        let flow = run_statements(context, &clause.condition, output).await?;
+
+        if let Some(flow) = after_body(flow) {
+            return Ok(flow);
+        }
+
+        if context.status().success() == clause.until {
+            break;
+        }
+
+        let flow = run_statements(context, &clause.body, output).await?;
+
+        if let Some(flow) = after_body(flow) {
+            return Ok(flow);
+        }
+
+        status = context.status();
+    }
+
+    context.set_status(status);
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Run the body for each field the words expand to, with the variable set to each, or each
+/// positional parameter when there are no words.
+///
+/// The status is that of the last body run.
+async fn run_for(context: &mut Context, clause: &For<'_>, output: &mut bool) -> Flow {
+    let mut words = match &clause.words {
+        Some(_words) => Vec::new(),
+        None => context.positional().to_vec(),
+    };
+
+    for arg in clause.words.iter().flatten() {
+        if substitutes(context, arg.as_str()) {
+            begin_output(context, output).await?;
+        }
+
+        match expand::fields(context, arg) {
+            Ok(fields) => words.extend(fields),
+            Err(error) => {
+                begin_output(context, output).await?;
+                context.report(arg.as_str(), &error).await?;
+                context.set_status(exit_status(1));
+
+                return Ok(ControlFlow::Continue(()));
+            }
+        }
+    }
+
+    let mut status = exit_status(0);
+
+    for word in words {
+        if let Err(error) = context.set_var(clause.name, word) {
+            begin_output(context, output).await?;
+            context.report(clause.name, &error).await?;
+            context.set_status(exit_status(1));
+
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        let flow = run_statements(context, &clause.body, output).await?;
+
+        if let Some(flow) = after_body(flow) {
+            return Ok(flow);
+        }
+
+        status = context.status();
+    }
+
+    context.set_status(status);
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Decide what a loop does after its condition or body ran, `None` to carry on, otherwise the
+/// flow of the loop itself.
+fn after_body(flow: ControlFlow<Jump>) -> Option<ControlFlow<Jump>> {
+    match flow {
+        ControlFlow::Continue(()) | ControlFlow::Break(Jump::Continue(1)) => None,
+        ControlFlow::Break(Jump::Break(1)) => Some(ControlFlow::Continue(())),
+        ControlFlow::Break(Jump::Break(count)) => Some(ControlFlow::Break(Jump::Break(count - 1))),
+        ControlFlow::Break(Jump::Continue(count)) => {
+            Some(ControlFlow::Break(Jump::Continue(count - 1)))
+        }
+        flow => Some(flow),
+    }
+}
+
+/// Run the body of the first arm with a pattern matching the word.
+async fn run_case(context: &mut Context, clause: &Case<'_>, output: &mut bool) -> Flow {
+    if substitutes(context, clause.word.as_str()) {
+        begin_output(context, output).await?;
+    }
+
+    let word = match expand::arg(context, &clause.word) {
+        Ok(word) => word,
+        Err(error) => {
+            begin_output(context, output).await?;
+            context.report(clause.word.as_str(), &error).await?;
+            context.set_status(exit_status(1));
+
+            return Ok(ControlFlow::Continue(()));
+        }
+    };
+
+    for arm in &clause.arms {
+        for pattern in &arm.patterns {
+            let pattern = match expand::pattern(context, pattern) {
+                Ok(pattern) => pattern,
+                Err(error) => {
+                    begin_output(context, output).await?;
+                    context.report(pattern.as_str(), &error).await?;
+                    context.set_status(exit_status(1));
+
+                    return Ok(ControlFlow::Continue(()));
+                }
+            };
+
+            if pattern::matches(&pattern, &word) {
+                return run_statements(context, &arm.body, output).await;
+            }
+        }
+    }
+
+    context.set_status(exit_status(0));
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Run a shell function, with its arguments as the positional parameters.
+///
+/// The status is that given to `return`, or of the last command run. Breaks if the function
+/// exits the shell, or is interrupted.
+async fn run_function(
+    context: &mut Context,
+    command: &Command<'_>,
+    source: &str,
+    output: &mut bool,
+) -> io::Result<ControlFlow<Jump, ExitStatus>> {
+    let mut args = Vec::new();
+
+    for arg in &command.args {
+        match expand::fields(context, arg) {
+            Ok(fields) => args.extend(fields),
+            Err(error) => {
+                begin_output(context, output).await?;
+                context.report(arg.as_str(), &error).await?;
+
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            }
+        }
+    }
+
+    let source = context.expand_aliases(source);
+    let script = match Script::try_parse(&source) {
+        Ok(script) => script,
+        Err(error) => {
+            let (target, error) = syntax_error(&error);
+
+            begin_output(context, output).await?;
+            context.report(&target, &error).await?;
+
+            return Ok(ControlFlow::Continue(exit_status(2)));
+        }
+    };
+
+    let positional = context.enter_function(args);
+    // loops outside of the function can't be broken from within it
+    let loops = mem::take(&mut context.loops);
+    let flow = run_statements(context, &script, output).await;
+
+    context.loops = loops;
+    context.leave_function(positional);
+
+    match flow? {
+        ControlFlow::Break(Jump::Return(status)) => Ok(ControlFlow::Continue(status)),
+        ControlFlow::Break(jump @ (Jump::Exit | Jump::Interrupt)) => Ok(ControlFlow::Break(jump)),
+        _flow => Ok(ControlFlow::Continue(context.status())),
+    }
+}
+
+/// Expand each argument of a builtin into fields, reporting the first which fails.
+async fn expand_args(
+    context: &mut Context,
+    command: &Command<'_>,
+    output: &mut bool,
+) -> io::Result<Option<Vec<String>>> {
+    let mut args = Vec::new();
+
+    for arg in &command.args {
+        match expand::fields(context, arg) {
+            Ok(fields) => args.extend(fields),
+            Err(error) => {
+                begin_output(context, output).await?;
+                context.report(arg.as_str(), &error).await?;
+
+                return Ok(None);
+            }
+        }
+    }
+
+    Ok(Some(args))
+}
+
+/// Expand the arguments of `command` without splitting, or globbing, as `export`, `local`, and
+/// `alias` take `name=value`, i.e. `local name=$1` is a single word.
+async fn expand_words(
+    context: &mut Context,
+    command: &Command<'_>,
+    output: &mut bool,
+) -> io::Result<Option<Vec<String>>> {
+    let mut words = Vec::new();
+
+    for arg in &command.args {
+        match expand::arg(context, arg) {
+            Ok(word) => words.push(word),
+            Err(error) => {
+                begin_output(context, output).await?;
+                context.report(arg.as_str(), &error).await?;
+
+                return Ok(None);
+            }
+        }
+    }
+
+    Ok(Some(words))
+}
+
+/// Split the leading `-P`, and `-L` flags from the arguments of `cd`, `pushd`, or `popd`,
+/// returning whether symbolic links are resolved.
+fn split_physical(args: &[String]) -> (bool, &[String]) {
+    let mut physical = false;
+    let mut args = args;
+
+    while let Some((flag, rest)) = args.split_first() {
+        match flag.as_str() {
+            "-P" => physical = true,
+            "-L" => physical = false,
+            "--" => return (physical, rest),
+            _ => break,
+        }
+
+        args = rest;
+    }
+
+    (physical, args)
+}
+
+/// Run `cd`, changing to `$HOME` without a target, and to `$OLDPWD` for `-`.
+///
+/// Relative targets are searched for within `CDPATH`. The new directory is printed when it
+/// isn't the one given.
+async fn run_cd(
+    context: &mut Context,
+    command: &Command<'_>,
+    output: &mut bool,
+) -> io::Result<ExitStatus> {
+    let Some(args) = expand_args(context, command, output).await? else {
+        return Ok(exit_status(1));
+    };
+
+    let (physical, args) = split_physical(&args);
+    let (target_dir, print) = match args {
+        [] => (context.home_dir.clone(), false),
+        [target] if target == "-" => match context.var("OLDPWD") {
+            Some(previous) => (PathBuf::from(previous), true),
+            None => {
+                let error = io::Error::new(io::ErrorKind::Other, "OLDPWD not set");
+
+                begin_output(context, output).await?;
+                context.report("cd", &error).await?;
+
+                return Ok(exit_status(1));
+            }
+        },
+        [target] => match context.find_dir(Path::new(target)) {
+            Some(found) => (found, true),
+            None => (PathBuf::from(target), false),
+        },
+        _ => {
+            let error = io::Error::new(io::ErrorKind::InvalidInput, "too many arguments");
+
+            begin_output(context, output).await?;
+            context.report("cd", &error).await?;
+
+            return Ok(exit_status(1));
+        }
+    };
+
+    if let Err(error) = context.change_dir(&target_dir, physical) {
+        begin_output(context, output).await?;
+        context
+            .report(&target_dir.display().to_string(), &error)
+            .await?;
+
+        return Ok(exit_status(1));
+    }
+
+    if print {
+        let edit = format!("{}\r\n", context.var("PWD").unwrap_or_default());
+
+        begin_output(context, output).await?;
+        context.write_all(edit.as_bytes()).await?;
+    }
+
+    Ok(exit_status(0))
+}
+
+/// Run `pushd`, `popd`, or `dirs`, which manage the directory stack.
+///
+/// `pushd`, and `popd` print the stack after changing directory. `dirs -v` numbers each
+/// directory, `dirs -p` prints one per line, and `dirs -c` empties the stack.
+async fn run_dirs(
+    context: &mut Context,
+    program: &str,
+    command: &Command<'_>,
+    output: &mut bool,
+) -> io::Result<ExitStatus> {
+    let Some(args) = expand_args(context, command, output).await? else {
+        return Ok(exit_status(1));
+    };
+
+    begin_output(context, output).await?;
+
+    let (physical, args) = split_physical(&args);
+    let mut target = program.to_string();
+    let mut verbose = false;
+    let mut per_line = false;
+
+    let result = match (program, args) {
+        ("pushd", []) => context.swap_dir(physical),
+        ("pushd", [name]) => {
+            let target_dir = context
+                .find_dir(Path::new(name))
+                .unwrap_or_else(|| PathBuf::from(name));
+
+            target = target_dir.display().to_string();
+            context.push_dir(&target_dir, physical)
+        }
+        ("popd", []) => context.pop_dir(physical),
+        ("dirs", flags) => {
+            for flag in flags {
+                match flag.as_str() {
+                    "-c" => context.clear_dirs(),
+                    "-p" => per_line = true,
+                    "-v" => verbose = true,
+                    _ => {
+                        let error = io::Error::new(io::ErrorKind::InvalidInput, "invalid option");
+
+                        context.report(flag, &error).await?;
+
+                        return Ok(exit_status(2));
+                    }
+                }
+            }
+
+            Ok(())
+        }
+        _ => {
+            let error = io::Error::new(io::ErrorKind::InvalidInput, "too many arguments");
+
+            context.report(program, &error).await?;
+
+            return Ok(exit_status(2));
+        }
+    };
+
+    if let Err(error) = result {
+        context.report(&target, &error).await?;
+
+        return Ok(exit_status(1));
+    }
+
+    let mut edit = String::new();
+
+    for (index, dir) in context.dirs().enumerate() {
+        let dir = context.shorten_path(dir);
+        let dir = dir.display();
+
+        let _ = match (verbose, per_line) {
+            (true, _) => write!(edit, "{index:2}  {dir}\r\n"),
+            (false, true) => write!(edit, "{dir}\r\n"),
+            (false, false) if index == 0 => write!(edit, "{dir}"),
+            (false, false) => write!(edit, " {dir}"),
+        };
+    }
+
+    if !verbose && !per_line {
+        edit.push_str("\r\n");
+    }
+
+    context.write_all(edit.as_bytes()).await?;
+
+    Ok(exit_status(0))
+}
+
+/// Run `history`, listing each command recorded, or `history delete`, removing those matching
+/// from memory, and the history file.
+///
+/// `delete` matches the whole command by default, or with `--prefix`, or `--contains`, part of
+/// it.
+async fn run_history(
+    context: &mut Context,
+    command: &Command<'_>,
+    output: &mut bool,
+) -> io::Result<ExitStatus> {
+    let Some(args) = expand_args(context, command, output).await? else {
+        return Ok(exit_status(1));
+    };
+
+    begin_output(context, output).await?;
+
+    let texts = match args.split_first() {
+        None => {
+            let mut edit = String::new();
+
+            for (index, record) in context.history().enumerate() {
+                let text = record.text.replace('\n', "\r\n       ");
+                let _ = write!(edit, "{:5}  {text}\r\n", index + 1);
+            }
+
+            context.write_all(edit.as_bytes()).await?;
+
+            return Ok(exit_status(0));
+        }
+        Some((subcommand, texts)) if subcommand == "delete" => texts,
+        Some((subcommand, _texts)) => {
+            let error = io::Error::new(io::ErrorKind::InvalidInput, "unknown subcommand");
+
+            context.report(subcommand, &error).await?;
+
+            return Ok(exit_status(2));
+        }
+    };
+
+    let (kind, texts) = match texts.split_first() {
+        Some((flag, texts)) if flag.starts_with("--") => (flag.as_str(), texts),
+        _ => ("--exact", texts),
+    };
+
+    if !matches!(kind, "--exact" | "--prefix" | "--contains") {
+        let error = io::Error::new(io::ErrorKind::InvalidInput, "invalid option");
+
+        context.report(kind, &error).await?;
+
+        return Ok(exit_status(2));
+    }
+
+    if texts.is_empty() {
+        let error = io::Error::new(io::ErrorKind::InvalidInput, "requires a command to delete");
+
+        context.report("history", &error).await?;
+
+        return Ok(exit_status(2));
+    }
+
+    let result = context.delete_history(|record| {
+        texts.iter().any(|text| match kind {
+            "--prefix" => record.starts_with(text.as_str()),
+            "--contains" => record.contains(text.as_str()),
+            _ => record == text,
+        })
+    });
+
+    match result {
+        Ok(0) => {
+            let error = io::Error::new(io::ErrorKind::Other, "not in history");
+
+            context.report(&texts.join(" "), &error).await?;
+
+            Ok(exit_status(1))
+        }
+        Ok(_removed) => Ok(exit_status(0)),
+        Err(error) => {
+            context.report("history", &error).await?;
+
+            Ok(exit_status(1))
+        }
+    }
+}
+
+/// Run `export`, or `readonly`, setting each `name=value`, and marking each name given.
+///
+/// Without names, lists the variables which are marked. `export -n` stops exporting instead.
+async fn run_declare(
+    context: &mut Context,
+    program: &str,
+    command: &Command<'_>,
+    output: &mut bool,
+) -> io::Result<ExitStatus> {
+    let Some(words) = expand_words(context, command, output).await? else {
+        return Ok(exit_status(1));
+    };
+
+    begin_output(context, output).await?;
+
+    let (mark, words) = match words.split_first() {
+        Some((flag, words)) if program == "export" && flag == "-n" => (false, words),
+        Some((flag, words)) if flag == "-p" => (true, words),
+        _ => (true, words.as_slice()),
+    };
+
+    if words.is_empty() {
+        let mut edit = String::new();
+
+        for (name, variable) in context.variables() {
+            let marked = match program {
+                "export" => variable.exported,
+                _ => variable.readonly,
+            };
+
+            if !marked {
+                continue;
+            }
+
+            match &variable.value {
+                Some(value) => {
+                    let _ = write!(edit, "{program} {name}={}\r\n", single_quote(value));
+                }
+                None => {
+                    let _ = write!(edit, "{program} {name}\r\n");
+                }
+            }
+        }
+
+        context.write_all(edit.as_bytes()).await?;
+
+        return Ok(exit_status(0));
+    }
+
+    let mut status = exit_status(0);
+
+    for word in words {
+        let (name, value) = match word.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (word.as_str(), None),
+        };
+
+        if !is_name(name) {
+            let error = io::Error::new(io::ErrorKind::InvalidInput, "not a valid name");
+
+            context.report(name, &error).await?;
+            status = exit_status(1);
+
+            continue;
+        }
+
+        if let Some(value) = value {
+            if let Err(error) = context.set_var(name, value) {
+                context.report(name, &error).await?;
+                status = exit_status(1);
+
+                continue;
+            }
+        }
+
+        match program {
+            "export" => context.export_var(name, mark),
+            _ => context.make_readonly(name),
+        }
+    }
+
+    Ok(status)
+}
+
+/// Run a file in the current shell, `source file [args...]`, with any arguments as the
+/// positional parameters.
+///
+/// The status is that given to `return`, or of the last command run. Breaks if the file exits
+/// the shell, or is interrupted.
+async fn run_sourced(
+    context: &mut Context,
+    program: &str,
+    command: &Command<'_>,
+    output: &mut bool,
+) -> io::Result<ControlFlow<Jump, ExitStatus>> {
+    let mut args = Vec::new();
+
+    for arg in &command.args {
+        match expand::fields(context, arg) {
+            Ok(fields) => args.extend(fields),
+            Err(error) => {
+                begin_output(context, output).await?;
+                context.report(arg.as_str(), &error).await?;
+
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            }
+        }
+    }
+
+    let (name, positional) = match args.split_first() {
+        Some(split) => split,
+        None => {
+            let error = io::Error::new(io::ErrorKind::InvalidInput, "requires a file");
+
+            begin_output(context, output).await?;
+            context.report(program, &error).await?;
+
+            return Ok(ControlFlow::Continue(exit_status(2)));
+        }
+    };
+
+    let path = match context.find_source(name) {
+        Some(path) if context.sources < MAX_SOURCE_DEPTH => path,
+        Some(_path) => {
+            let error = io::Error::new(io::ErrorKind::Other, "sourced too deeply");
+
+            begin_output(context, output).await?;
+            context.report(name, &error).await?;
+
+            return Ok(ControlFlow::Continue(exit_status(1)));
+        }
+        None => {
+            begin_output(context, output).await?;
+            context
+                .report(name, &io::Error::from(io::ErrorKind::NotFound))
+                .await?;
+
+            return Ok(ControlFlow::Continue(exit_status(1)));
+        }
+    };
+
+    // without arguments, the file sees the current positional parameters
+    let previous =
+        (!positional.is_empty()).then(|| context.replace_positional(positional.to_vec()));
+    // loops outside of the file can't be broken from within it
+    let loops = mem::take(&mut context.loops);
+
+    context.sources += 1;
+
+    let flow = run_file(context, &path, output).await;
+
+    context.sources -= 1;
+    context.loops = loops;
+
+    if let Some(previous) = previous {
+        context.replace_positional(previous);
+    }
+
+    match flow? {
+        ControlFlow::Break(Jump::Return(status)) => Ok(ControlFlow::Continue(status)),
+        ControlFlow::Break(jump @ (Jump::Exit | Jump::Interrupt)) => Ok(ControlFlow::Break(jump)),
+        _flow => Ok(ControlFlow::Continue(context.status())),
+    }
+}
+
+/// Quote a string within single quotes, so it may be entered again as-is.
+fn single_quote(string: &str) -> String {
+    format!("'{}'", string.replace('\'', r"'\''"))
+}
+
+/// Describe a syntax error, as the target to report, and the error.
+fn syntax_error(error: &ScriptError<'_>) -> (String, io::Error) {
+    let (target, message) = match error {
+        ScriptError::Command(_error) => ("", "invalid command"),
+        ScriptError::Incomplete(expected) => (*expected, "expected"),
+        ScriptError::Unexpected(unexpected) => (*unexpected, "unexpected"),
+    };
+
+    (
+        target.into(),
+        io::Error::new(io::ErrorKind::InvalidInput, message),
+    )
+}
+
+/// Run a pipeline, or a builtin if the pipeline is a single command.
+///
+/// Builtins always run in the foreground. Breaks if the builtin stops the script.
+async fn run_pipeline(
+    context: &mut Context,
+    pipeline: &Pipeline<'_>,
+    background: bool,
+    output: &mut bool,
+) -> io::Result<ControlFlow<Jump, ExitStatus>> {
+    if pipeline.commands.len() > 1 {
+        begin_output(context, output).await?;
+
+        let status = spawn_pipeline(context, pipeline, background).await?;
+
+        return Ok(ControlFlow::Continue(
+            status.unwrap_or_else(|_| exit_status(1)),
+        ));
+    }
+
+    if substitutes(context, pipeline.string) {
+        begin_output(context, output).await?;
+    }
+
+    let command = pipeline.last();
+
+    // builtins write to the terminal, not the streams of a process
+    if !command.redirects.is_empty() && paths::BUILTINS.contains(&command.program.as_str()) {
+        let error = io::Error::new(io::ErrorKind::Other, "can't be redirected");
+
+        begin_output(context, output).await?;
+        context.report(command.program.as_str(), &error).await?;
+
+        return Ok(ControlFlow::Continue(exit_status(1)));
+    }
+
+    let status = match command.program.as_str() {
+        "exit" => {
+            if let Some(arg) = command.args.get(0) {
+                match expand::arg(context, arg).map(|code| code.parse()) {
+                    Ok(Ok(code)) => context.set_status(exit_status(code)),
+                    _ => {
+                        let error = io::Error::new(io::ErrorKind::InvalidInput, "invalid status");
+
+                        begin_output(context, output).await?;
+                        context.report(arg.as_str(), &error).await?;
+                        context.set_status(exit_status(2));
+                    }
+                }
+            }
+
+            return Ok(ControlFlow::Break(Jump::Exit));
+        }
+        "local" => {
+            let Some(words) = expand_words(context, command, output).await? else {
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            };
+
+            let mut status = exit_status(0);
+
+            for word in words {
+                let (name, value) = match word.split_once('=') {
+                    Some((name, value)) => (name, Some(String::from(value))),
+                    None => (word.as_str(), None),
+                };
+
+                if let Err(error) = context.set_local(name, value) {
+                    begin_output(context, output).await?;
+                    context.report(name, &error).await?;
+                    status = exit_status(1);
+
+                    break;
+                }
+            }
+
+            status
+        }
+        "return" => {
+            let status = match command.args.get(0) {
+                Some(arg) => match expand::arg(context, arg) {
+                    Ok(code) => code.parse().ok().map(exit_status),
+                    Err(_error) => None,
+                },
+                None => Some(context.status()),
+            };
+
+            match status {
+                Some(status) if context.in_function() || context.sources > 0 => {
+                    return Ok(ControlFlow::Break(Jump::Return(status)));
+                }
+                Some(_status) => {
+                    let error = io::Error::new(
+                        io::ErrorKind::Other,
+                        "only meaningful in a function, or sourced file",
+                    );
+
+                    begin_output(context, output).await?;
+                    context.report("return", &error).await?;
+
+                    exit_status(1)
+                }
+                None => {
+                    let error = io::Error::new(io::ErrorKind::InvalidInput, "invalid status");
+
+                    begin_output(context, output).await?;
+                    context.report(command.args[0].as_str(), &error).await?;
+
+                    exit_status(2)
+                }
+            }
+        }
+        program @ ("break" | "continue") => {
+            let Some(args) = expand_args(context, command, output).await? else {
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            };
+
+            let count = match args.first() {
+                Some(arg) => arg.parse().ok().filter(|count| *count > 0),
+                None => Some(1),
+            };
+
+            match count {
+                Some(count) if context.loops > 0 => {
+                    let count = count.min(context.loops);
+
+                    context.set_status(exit_status(0));
+
+                    return Ok(ControlFlow::Break(if program == "break" {
+                        Jump::Break(count)
+                    } else {
+                        Jump::Continue(count)
+                    }));
+                }
+                Some(_count) => {
+                    let error = io::Error::new(io::ErrorKind::Other, "only meaningful in a loop");
+
+                    begin_output(context, output).await?;
+                    context.report(program, &error).await?;
+
+                    exit_status(1)
+                }
+                None => {
+                    let error = io::Error::new(io::ErrorKind::InvalidInput, "invalid count");
+
+                    begin_output(context, output).await?;
+                    context.report(&args[0], &error).await?;
+
+                    exit_status(1)
+                }
+            }
+        }
+        "cd" => run_cd(context, command, output).await?,
+        "history" => run_history(context, command, output).await?,
+        program @ ("pushd" | "popd" | "dirs") => {
+            run_dirs(context, program, command, output).await?
+        }
+        "showkeys" => {
+            context.toggle_showkeys();
+
+            exit_status(0)
+        }
+        "jobs" => {
+            begin_output(context, output).await?;
+            context.list_jobs().await?;
+
+            exit_status(0)
+        }
+        "set" if command.args.first().map(|arg| arg.as_str()) == Some("--") => {
+            let mut positional = Vec::new();
+
+            for arg in &command.args[1..] {
+                match expand::fields(context, arg) {
+                    Ok(fields) => positional.extend(fields),
+                    Err(error) => {
+                        begin_output(context, output).await?;
+                        context.report(arg.as_str(), &error).await?;
+
+                        return Ok(ControlFlow::Continue(exit_status(1)));
+                    }
+                }
+            }
+
+            context.replace_positional(positional);
+
+            exit_status(0)
+        }
+        "set" => {
+            let Some(args) = expand_args(context, command, output).await? else {
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            };
+
+            let mut args = args.iter().map(String::as_str);
+
+            begin_output(context, output).await?;
+
+            match (args.next(), args.next()) {
+                (None, None) => {
+                    let mut edit = String::new();
+
+                    for (name, variable) in context.variables() {
+                        if let Some(value) = &variable.value {
+                            let _ = write!(edit, "{name}={}\r\n", single_quote(value));
+                        }
+                    }
+
+                    context.write_all(edit.as_bytes()).await?;
+
+                    exit_status(0)
+                }
+                (Some("-o"), None) => {
+                    let mut edit = String::new();
+
+                    for (name, on) in context.options.iter() {
+                        let on = if on { "on" } else { "off" };
+                        let _ = write!(edit, "{name:<16}{on}\r\n");
+                    }
+
+                    context.write_all(edit.as_bytes()).await?;
+
+                    exit_status(0)
+                }
+                (Some(flag @ ("-o" | "+o")), Some(name)) => match context.options.get_mut(name) {
+                    Some(option) => {
+                        *option = flag == "-o";
+
+                        exit_status(0)
+                    }
+                    None => {
+                        let error = io::Error::new(io::ErrorKind::Other, "no such option");
+
+                        context.report(name, &error).await?;
+
+                        exit_status(1)
+                    }
+                },
+                (flag, _name) => {
+                    let error = io::Error::new(io::ErrorKind::InvalidInput, "invalid option");
+
+                    context.report(flag.unwrap_or("set"), &error).await?;
+
+                    exit_status(2)
+                }
+            }
+        }
+        program @ ("export" | "readonly") => run_declare(context, program, command, output).await?,
+        "unset" => {
+            let Some(args) = expand_args(context, command, output).await? else {
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            };
+
+            let mut args = args.iter().map(String::as_str).peekable();
+            // `-f` removes functions, `-v`, the default, variables
+            let functions = args.next_if(|arg| matches!(*arg, "-f" | "-v")) == Some("-f");
+
+            let mut status = exit_status(0);
+
+            for name in args {
+                let result = if functions {
+                    context.remove_function(name);
+
+                    Ok(())
+                } else {
+                    context.unset_var(name)
+                };
+
+                if let Err(error) = result {
+                    begin_output(context, output).await?;
+                    context.report(name, &error).await?;
+                    status = exit_status(1);
+                }
+            }
+
+            status
+        }
+        "alias" => {
+            let Some(words) = expand_words(context, command, output).await? else {
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            };
+
+            let mut status = exit_status(0);
+
+            begin_output(context, output).await?;
+
+            if words.is_empty() {
+                let mut edit = String::new();
+
+                for (name, value) in context.aliases() {
+                    let _ = write!(edit, "alias {name}={}\r\n", single_quote(value));
+                }
+
+                context.write_all(edit.as_bytes()).await?;
+            } else {
+                for word in words {
+                    match word.split_once('=') {
+                        Some((name, value)) if !name.is_empty() => {
+                            context.define_alias(name, value)
+                        }
+                        _ => match context.alias(&word) {
+                            Some(value) => {
+                                let edit = format!("alias {word}={}\r\n", single_quote(value));
+
+                                context.write_all(edit.as_bytes()).await?;
+                            }
+                            None => {
+                                let error = io::Error::new(io::ErrorKind::Other, "no such alias");
+
+                                context.report(&word, &error).await?;
+                                status = exit_status(1);
+                            }
+                        },
+                    }
+                }
+            }
+
+            status
+        }
+        "unalias" => {
+            let Some(args) = expand_args(context, command, output).await? else {
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            };
+
+            let mut status = exit_status(0);
+
+            for name in &args {
+                if !context.remove_alias(name) {
+                    let error = io::Error::new(io::ErrorKind::Other, "no such alias");
+
+                    begin_output(context, output).await?;
+                    context.report(name, &error).await?;
+                    status = exit_status(1);
+                }
+            }
+
+            status
+        }
+        "abbr" => {
+            let Some(words) = expand_words(context, command, output).await? else {
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            };
+
+            begin_output(context, output).await?;
+
+            match words.as_slice() {
+                [] => {
+                    let mut edit = String::new();
+
+                    for (name, expansion) in context.abbreviations() {
+                        let _ = write!(edit, "abbr {name} {}\r\n", single_quote(expansion));
+                    }
+
+                    context.write_all(edit.as_bytes()).await?;
+
+                    exit_status(0)
+                }
+                [flag, names @ ..] if flag == "-e" || flag == "--erase" => {
+                    let mut status = exit_status(0);
+
+                    for name in names {
+                        if !context.remove_abbreviation(name) {
+                            let error =
+                                io::Error::new(io::ErrorKind::Other, "no such abbreviation");
+
+                            context.report(name, &error).await?;
+                            status = exit_status(1);
+                        }
+                    }
+
+                    status
+                }
+                [name] => {
+                    let error = io::Error::new(io::ErrorKind::InvalidInput, "missing expansion");
+
+                    context.report(name, &error).await?;
+
+                    exit_status(2)
+                }
+                [name, expansion @ ..] => {
+                    context.define_abbreviation(name, &expansion.join(" "));
+
+                    exit_status(0)
+                }
+            }
+        }
+        program @ ("fg" | "bg" | "wait") => {
+            let Some(args) = expand_args(context, command, output).await? else {
+                return Ok(ControlFlow::Continue(exit_status(1)));
+            };
+
+            let spec = args.first().map(String::as_str);
+
+            begin_output(context, output).await?;
+            context.reap_jobs();
+
+            match (program, context.jobs.find(spec)) {
+                ("fg", Some(id)) => context.resume(id, true).await?,
+                ("bg", Some(id)) => context.resume(id, false).await?,
+                ("wait", Some(id)) => context.wait(Some(id)),
+                ("wait", None) if spec.is_none() => context.wait(None),
+                _ => {
+                    let error = io::Error::new(io::ErrorKind::Other, "no such job");
+
+                    context.report(spec.unwrap_or(program), &error).await?;
+
+                    exit_status(1)
+                }
+            }
+        }
+        program @ ("source" | ".") => return run_sourced(context, program, command, output).await,
+        "" if !command.vars.is_empty() && command.program.quote().is_none() => {
+            // the status is that of the last command substitution, if any
+            context.set_status(exit_status(0));
+
+            for var in &command.vars {
+                if let Var::Pair(name, value) = var {
+                    let result = expand::value(context, value)
+                        .and_then(|value| context.set_var(name, value));
+
+                    if let Err(error) = result {
+                        begin_output(context, output).await?;
+                        context.report(name, &error).await?;
+
+                        return Ok(ControlFlow::Continue(exit_status(1)));
+                    }
+                }
+            }
+
+            context.status()
+        }
+        program => {
+            // with redirections, it runs in a subshell
+            if let Some(source) = context.function(program) {
+                if command.redirects.is_empty() {
+                    return run_function(context, command, &source, output).await;
+                }
+            }
+
+            let had_output = *output;
+
+            begin_output(context, output).await?;
+
+            match spawn_pipeline(context, pipeline, background).await? {
+                Ok(status) => status,
+                Err(error) => {
+                    // not a program, perhaps a directory
+                    let target_dir = context.expand_path(program);
+
+                    match context.change_dir(&target_dir, false) {
+                        Ok(()) => {
+                            if !had_output {
+                                context.write_all(b"\x1b[A").await?;
+                                *output = false;
+                            }
+
+                            exit_status(0)
+                        }
+                        Err(_error) => {
+                            context.report(program, &error).await?;
+
+                            exit_status(127)
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(ControlFlow::Continue(status))
+}
+
+/// Spawn a pipeline as a job, forking a subshell for each function, and builtin, as they can't
+/// run within the shell while the rest of the pipeline does, nor have their output redirected.
+async fn spawn_pipeline(
+    context: &mut Context,
+    pipeline: &Pipeline<'_>,
+    background: bool,
+) -> io::Result<io::Result<ExitStatus>> {
+    let stages: Vec<_> = pipeline
+        .commands
+        .iter()
+        .map(|command| stage(context, command))
+        .collect();
+
+    let run = |context: &mut Context, index: usize| {
+        run_alone(context, &pipeline.commands[index], pipeline.string)
+    };
+
+    context
+        .spawn(&stages, pipeline.string, background, &run)
+        .await
+}
+
+/// Run a pipeline of which a command is compound, or a compound command with redirections, each
+/// compound command in a subshell.
+async fn run_piped(context: &mut Context, piped: &Piped<'_>, output: &mut bool) -> Flow {
+    begin_output(context, output).await?;
+
+    let stages: Vec<_> = piped
+        .elements
+        .iter()
+        .map(|element| match element {
+            Element::Command(command) => stage(context, command),
+            Element::Compound(_statement, redirects) => Stage::Subshell(redirects),
+        })
+        .collect();
+
+    let run = |context: &mut Context, index: usize| match &piped.elements[index] {
+        Element::Command(command) => run_alone(context, command, piped.string),
+        Element::Compound(statement, _redirects) => {
+            let flow = block_on(run_statement(context, statement, &mut true));
+
+            subshell_status(context, flow)
+        }
+    };
+
+    let status = match context.spawn(&stages, piped.string, false, &run).await? {
+        Ok(status) => status,
+        Err(error) => {
+            context.report(piped.string.trim(), &error).await?;
+
+            exit_status(1)
+        }
+    };
+
+    context.set_status(status);
+
+    if status.signal() == Some(libc::SIGINT) {
+        return Ok(ControlFlow::Break(Jump::Interrupt));
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Returns how `command` runs as part of a pipeline, functions, and builtins in a subshell.
+fn stage<'p, 'a>(context: &Context, command: &'p Command<'a>) -> Stage<'p, 'a> {
+    let program = command.program.as_str();
+
+    if context.function(program).is_some() || paths::BUILTINS.contains(&program) {
+        Stage::Subshell(&command.redirects)
+    } else {
+        Stage::Program(command)
+    }
+}
+
+/// Run `command` alone in a subshell, which made its redirections already, returning the status
+/// to exit with. `string` is the source of its pipeline.
+fn run_alone(context: &mut Context, command: &Command<'_>, string: &str) -> ExitStatus {
+    let command = Command {
+        redirects: Vec::new(),
+        ..command.clone()
+    };
+
+    let list = List {
+        pipelines: vec![Pipeline {
+            commands: vec![command],
+            pipes: Vec::new(),
+            string,
+        }],
+        separators: Vec::new(),
+    };
+
+    let flow = block_on(run_list(context, &list, Operator::Semicolon, &mut true));
+
+    subshell_status(context, flow)
+}
+
+/// Report an error the shell can't go on from, and exit with `code`.
+fn fail(target: &str, message: &str, code: i32) -> ! {
+    eprintln!("elysh: `{target}` {message}");
+
+    process::exit(code)
+}
+
+/// Read all of stdin, as a script.
+fn read_stdin() -> String {
+    let mut source = String::new();
+
+    if let Err(error) = io::stdin().read_to_string(&mut source) {
+        fail("stdin", &error.to_string(), 1);
+    }
+
+    source
+}
+
+/// Run the shell, with the line editor when there are no arguments and stdin is a terminal.
+///
+/// Otherwise, it runs `elysh script [args...]`, `elysh -c command [name [args...]]`, or
+/// `elysh [-s [args...]]` reading the script from stdin, then exits with the last status.
+///
+/// A login shell, started with `-l`, or with a name beginning with `-`, runs the profile first.
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> io::Result<()> {
+    let mut args = env::args();
+    let shell = args.next().unwrap_or_else(|| String::from("elysh"));
+    let mut args: Vec<String> = args.collect();
+    let mut login = shell.starts_with('-');
+
+    if args.first().map(String::as_str) == Some("-l") {
+        args.remove(0);
+        login = true;
+    }
+
+    // SAFETY: `isatty` is always safe to call.
+    let is_tty = unsafe { libc::isatty(libc::STDIN_FILENO) } == 1;
+
+    let (origin, name, source, positional) = match args.split_first() {
+        None if is_tty => return interactive(login).await,
+        None => ("stdin".into(), shell, read_stdin(), Vec::new()),
+        Some((flag, rest)) if flag == "-c" => match rest {
+            [command, name, positional @ ..] => (
+                flag.clone(),
+                name.clone(),
+                command.clone(),
+                positional.to_vec(),
+            ),
+            [command] => (flag.clone(), shell, command.clone(), Vec::new()),
+            [] => fail(flag, "requires an argument", 2),
+        },
+        Some((flag, positional)) if flag == "-s" => {
+            ("stdin".into(), shell, read_stdin(), positional.to_vec())
+        }
+        Some((flag, _rest)) if flag.starts_with('-') => fail(flag, "invalid option", 2),
+        Some((path, positional)) => match fs::read_to_string(path) {
+            Ok(source) => (path.clone(), path.clone(), source, positional.to_vec()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                fail(path, "no such file or directory", 127)
+            }
+            Err(error) => fail(path, &error.to_string(), 126),
+        },
+    };
+
+    let status = run_noninteractive(&origin, name, &source, positional, login).await?;
+
+    process::exit(status_code(status))
+}
+
+/// Run a whole script without a terminal, returning the status to exit with.
+///
+/// `origin` names the script in syntax errors. Aliases aren't expanded, as the script is parsed
+/// before any are defined.
+async fn run_noninteractive(
+    origin: &str,
+    name: String,
+    source: &str,
+    positional: Vec<String>,
+    login: bool,
+) -> io::Result<ExitStatus> {
+    let mut context = Context::new(None).await?;
+    // there is no input line to move below
+    let mut output = true;
+
+    if run_startup(&mut context, login, &mut output)
+        .await?
+        .is_break()
+    {
+        return Ok(context.status());
+    }
+
+    context.set_args(name, positional);
+
+    // whether it ran to the end, or stopped early on `exit`, the status is the last
+    let _flow = run_source(&mut context, origin, source, &mut output).await?;
+
+    Ok(context.status())
+}
+
+/// Run the profile of a login shell, then the config of an interactive shell, of those which
+/// exist.
+///
+/// Neither failing stops the shell from starting. Breaks if either exits the shell.
+async fn run_startup(context: &mut Context, login: bool, output: &mut bool) -> Flow {
+    let profile = login.then(|| context.profile_path());
+    let config = context.is_interactive().then(|| context.config_path());
+
+    for path in profile.into_iter().chain(config) {
+        if !path.is_file() {
+            continue;
+        }
+
+        if let ControlFlow::Break(Jump::Exit) = run_file(context, &path, output).await? {
+            return Ok(ControlFlow::Break(Jump::Exit));
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Run the script at `path`.
+///
+/// A file which can't be read is reported, with status 1.
+async fn run_file(context: &mut Context, path: &Path, output: &mut bool) -> Flow {
+    let origin = context.shorten_path(path).display().to_string();
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            begin_output(context, output).await?;
+            context.report(&origin, &error).await?;
+            context.set_status(exit_status(1));
+
+            return Ok(ControlFlow::Continue(()));
+        }
+    };
+
+    run_source(context, &origin, &source, output).await
+}
+
+/// Run a whole script, or report where its syntax error is, as `origin:line:`, with status 2.
+async fn run_source(context: &mut Context, origin: &str, source: &str, output: &mut bool) -> Flow {
+    match Script::try_parse(source) {
+        Ok(script) => run_statements(context, &script, output).await,
+        Err(error) => {
+            let line = error.line(source);
+            let (target, error) = syntax_error(&error);
+
+            begin_output(context, output).await?;
+            context.report_at(origin, line, &target, &error).await?;
+            context.set_status(exit_status(2));
+
+            Ok(ControlFlow::Continue(()))
+        }
+    }
+}
+
+/// Run the shell with the line editor, until `exit`, or Ctrl-D.
+async fn interactive(login: bool) -> io::Result<()> {
+    let tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .await?;
+
+    let mut context = Context::new(Some(tty)).await?;
+
+    context.enable_raw().await?;
+
+    // nothing has been typed, there is no input line to move below
+    let exit = run_startup(&mut context, login, &mut true)
+        .await?
+        .is_break();
+
+    if !exit {
+        context.pre_prompt().await?;
+
+        loop {
+            context.write_all(render(&context).as_bytes()).await?;
+
+            let input = context.next_input().await?;
+
+            if context.search().is_some() {
+                if search_input(&mut context, &input) {
+                    continue;
+                }
+
+                // show the match accepted as any other line
+                context.write_all(render(&context).as_bytes()).await?;
+            }
+
+            match input {
+                Input::ArrowUp if input.none() => context.history_up(),
+                Input::Key('p') if input.ctrl() => context.history_up(),
+                Input::ArrowDown if input.none() => context.history_down(),
+                Input::Key('n') if input.ctrl() => context.history_down(),
+                Input::Key('r') if input.ctrl() => context.search_older(),
+                /*Input::ArrowRight if input.none() => {
+                    if let Summary::Partial(partial, rest) = &summary {
+                        if context.edit.at_end() {
+                            context.edit = format!("{partial}{rest}").into();
+                            context.edit.to_end();
+                        }
+                    }
+
+                    context.edit.right(1);
+                }
+                Input::Key('i') if input.ctrl() => {
+                    if let Summary::Partial(partial, rest) = &summary {
+                        if context.edit.at_end() {
+                            context.edit = format!("{partial}{rest}").into();
+                            context.edit.to_end();
+                        }
+                    }
+                    context.edit.right(1);
+                }*/
+                Input::ArrowLeft if input.none() => context.prev(),
+                Input::ArrowRight if input.none() => context.next(),
+                Input::Key('c') if input.ctrl() => context.clear(),
+                Input::Key('d') if input.ctrl() => break,
+                Input::Key('m') if input.ctrl() => {
+                    if !context.edit.is_empty() || context.is_continuation() {
+                        context.expand_abbreviation();
+                        context.execute_edit = true;
+                    }
+                }
+                Input::Key('w') if input.ctrl() => context.remove_word(WORD_CHARS),
+                Input::Key('k') if input.ctrl() => context.remove_end(),
+
+                Input::ArrowLeft if input.ctrl() => context.prev_word(WORD_CHARS),
+                Input::ArrowLeft if input.shift() => context.prev_word(WORD_CHARS),
+                Input::Key('b') if input.meta() => context.prev_word(WORD_CHARS),
+
+                Input::ArrowRight if input.ctrl() => context.next_word(WORD_CHARS),
+                Input::ArrowRight if input.shift() => context.next_word(WORD_CHARS),
+                Input::Key('f') if input.meta() => context.next_word(WORD_CHARS),
+
+                Input::Backspace if input.none() => context.remove(),
+                Input::Space if input.none() => {
+                    context.expand_abbreviation();
+                    context.insert(' ');
+                }
+                Input::Home if input.none() => context.to_start(),
+                Input::Key('a') if input.ctrl() => context.to_start(),
+                Input::End if input.none() => context.to_end(),
+                Input::Key('e') if input.ctrl() => context.to_end(),
+                Input::Key(key) if input.none() => context.insert(key),
+                Input::Paste(string) if input.none() => context.insert_str(&string),
+                _ => {}
+            }
+
+            if context.should_execute().is_some() {
+                let source = match context.take_source() {
+                    Some(source) => source,
+                    None => {
+                        // the here-document continues on the next line
+                        context.write_all(b"\r\n").await?;
+
+                        continue;
+                    }
+                };
+
+                // keep each line of a command which spans lines
+                let mut output = false;
+
+                if source.contains('\n') {
+                    begin_output(&context, &mut output).await?;
+                }
+
+                let source = context.expand_aliases(&source);
+
+                match Script::try_parse(&source) {
+                    Ok(script) => {
+                        let flow = run_script(&mut context, &script, output).await?;
+
+                        context.finish_history();
+
+                        if flow.is_break() {
+                            break;
+                        }
+                    }
+                    Err(error) => {
+                        let (target, error) = syntax_error(&error);
+
+                        begin_output(&context, &mut output).await?;
+                        context.report(&target, &error).await?;
+                        context.set_status(exit_status(2));
+                        context.finish_history();
+                        context.pre_prompt().await?;
+                    }
+                }
+            }
         }
     }
 
-    context.save_history().await;
     context.disable_raw().await?;
-    context.session.write_all(b"\r\n\n[elysh exited]\r\n").await?;
+    context.write_all(b"\r\n\n[elysh exited]\r\n").await?;
 
     Ok(())
 }
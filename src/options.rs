@@ -0,0 +1,51 @@
+/// Shell options, turned on with `set -o name`, and off with `set +o name`.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Run commands within backticks as command substitution, like POSIX shells.
+    pub backticks: bool,
+    /// Globs which match nothing fail the command, instead of being passed as-is.
+    pub failglob: bool,
+    /// Globs which match nothing are removed, instead of being passed as-is.
+    pub nullglob: bool,
+    /// Commands other sessions run are added to history as they finish, instead of only being
+    /// seen by sessions started later.
+    pub sharehistory: bool,
+}
+
+impl Options {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            backticks: false,
+            failglob: false,
+            nullglob: false,
+            sharehistory: false,
+        }
+    }
+
+    /// Returns the option named `name`.
+    #[inline]
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        let option = match name {
+            "backticks" => &mut self.backticks,
+            "failglob" => &mut self.failglob,
+            "nullglob" => &mut self.nullglob,
+            "sharehistory" => &mut self.sharehistory,
+            _ => return None,
+        };
+
+        Some(option)
+    }
+
+    /// Iterate each option's name and whether it is on.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, bool)> {
+        [
+            ("backticks", self.backticks),
+            ("failglob", self.failglob),
+            ("nullglob", self.nullglob),
+            ("sharehistory", self.sharehistory),
+        ]
+        .into_iter()
+    }
+}
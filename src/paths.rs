@@ -1,16 +1,25 @@
 use elysh_theme::Style;
 use fst::automaton::Str;
 use fst::{Automaton, IntoStreamer, Set};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs::Metadata;
 use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
 use std::{env, fmt, hint};
 use tokio::{fs, io};
 
+/// Commands the shell runs itself.
+pub const BUILTINS: &[&str] = &[
+    ".", "abbr", "alias", "bg", "break", "cd", "continue", "dirs", "exit", "export", "fg",
+    "history", "jobs", "local", "popd", "pushd", "readonly", "return", "set", "showkeys", "source",
+    "unalias", "unset", "wait",
+];
+
 /// A map of executables.
 pub struct Exes {
     set: Set<Vec<u8>>,
+    /// Names which were added, and aren't files.
+    added: BTreeSet<String>,
 }
 
 impl Exes {
@@ -20,8 +29,9 @@ impl Exes {
         unsafe {
             let iter = set.keys();
             let set = Set::from_iter(iter).unwrap_unchecked();
+            let added = BTreeSet::new();
 
-            Self { set }
+            Self { set, added }
         }
     }
 
@@ -33,6 +43,51 @@ impl Exes {
         Ok(Self::new(&exes))
     }
 
+    /// Add a name which isn't a file, i.e. a shell function, or an alias.
+    #[inline]
+    pub fn insert(&mut self, name: &str) {
+        if self.set.contains(name) {
+            return;
+        }
+
+        let mut names = self.names();
+
+        names.insert(name.into());
+        self.added.insert(name.into());
+        self.set_names(&names);
+    }
+
+    /// Remove a name which was added by `insert`.
+    #[inline]
+    pub fn remove(&mut self, name: &str) {
+        if !self.added.remove(name) {
+            return;
+        }
+
+        let mut names = self.names();
+
+        names.remove(name);
+        self.set_names(&names);
+    }
+
+    /// Returns every name.
+    #[inline]
+    fn names(&self) -> BTreeSet<String> {
+        self.set
+            .stream()
+            .into_strs()
+            .unwrap_or_default()
+            .into_iter()
+            .collect()
+    }
+
+    /// Replace every name.
+    #[inline]
+    fn set_names(&mut self, names: &BTreeSet<String>) {
+        // SAFETY: a `BTreeSet` is always sorted, and never contains duplicates.
+        self.set = unsafe { Set::from_iter(names).unwrap_unchecked() };
+    }
+
     /// Search for executables by the provided query.
     #[inline]
     fn fst_search(&self, query: &str) -> Vec<String> {
@@ -192,8 +247,16 @@ async fn from_env() -> io::Result<BTreeMap<String, PathBuf>> {
     let bins = {
         let mut bins = BTreeMap::new();
 
-        bins.insert("cd".into(), "<builtin>".into());
-        bins.insert("showkeys".into(), "<builtin>".into());
+        for &builtin in BUILTINS {
+            bins.insert(builtin.into(), "<builtin>".into());
+        }
+
+        for keyword in [
+            "case", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "then",
+            "until", "while",
+        ] {
+            bins.insert(keyword.into(), "<keyword>".into());
+        }
 
         for bin in set {
             let metadata = match fs::metadata(&bin).await {
@@ -0,0 +1,228 @@
+/// Does `string` match the glob `pattern`?
+///
+/// `*` matches any run of characters, `?` any single character, and `[...]` any character in
+/// the class, which may contain ranges like `a-z`, and is negated by a leading `!` or `^`. A
+/// backslash matches the next character literally.
+#[inline]
+pub fn matches(pattern: &str, string: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let string: Vec<char> = string.chars().collect();
+
+    matches_chars(&pattern, &string)
+}
+
+/// Escape the glob characters of `string`, so it matches only itself.
+#[inline]
+pub fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+
+    for character in string.chars() {
+        if matches!(character, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+/// Does `pattern` contain any unescaped glob characters?
+#[inline]
+pub fn is_pattern(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => {
+                chars.next();
+            }
+            '*' | '?' | '[' => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+#[inline]
+fn matches_chars(pattern: &[char], string: &[char]) -> bool {
+    let mut pattern_index = 0;
+    let mut string_index = 0;
+    // where to resume when the last `*` has to match one more character
+    let mut backtrack = None;
+
+    while string_index < string.len() {
+        let next = match pattern.get(pattern_index) {
+            Some('*') => {
+                backtrack = Some((pattern_index, string_index));
+                pattern_index += 1;
+
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match class(&pattern[pattern_index..], string[string_index]) {
+                Some((true, len)) => Some(len),
+                Some((false, _len)) => None,
+                // an unterminated class is a literal `[`
+                None => (string[string_index] == '[').then(|| 1),
+            },
+            Some('\\') if pattern_index + 1 < pattern.len() => {
+                (pattern[pattern_index + 1] == string[string_index]).then(|| 2)
+            }
+            Some(character) => (*character == string[string_index]).then(|| 1),
+            None => None,
+        };
+
+        match (next, backtrack) {
+            (Some(len), _) => {
+                pattern_index += len;
+                string_index += 1;
+            }
+            (None, Some((star, start))) => {
+                backtrack = Some((star, start + 1));
+                pattern_index = star + 1;
+                string_index = start + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+
+    pattern[pattern_index..]
+        .iter()
+        .all(|character| *character == '*')
+}
+
+/// Match `character` against the class at the start of `pattern`.
+///
+/// Returns whether it matched, and the length of the class, or `None` if the class is never
+/// closed.
+#[inline]
+fn class(pattern: &[char], character: char) -> Option<(bool, usize)> {
+    let mut index = 1;
+    let negate = matches!(pattern.get(index), Some('!' | '^'));
+
+    if negate {
+        index += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        let start = match pattern.get(index) {
+            // `]` first in the class is literal
+            Some(']') if !first => break,
+            Some('\\') => {
+                index += 1;
+
+                *pattern.get(index)?
+            }
+            Some(start) => *start,
+            None => return None,
+        };
+
+        first = false;
+        index += 1;
+
+        let end = match (pattern.get(index), pattern.get(index + 1)) {
+            (Some('-'), Some(end)) if *end != ']' => {
+                index += 2;
+
+                *end
+            }
+            _ => start,
+        };
+
+        if (start..=end).contains(&character) {
+            matched = true;
+        }
+    }
+
+    Some((matched != negate, index + 1))
+}
+
+/// Remove the shortest, or longest, prefix of `string` matching `pattern`.
+#[inline]
+pub fn remove_prefix<'a>(string: &'a str, pattern: &str, longest: bool) -> &'a str {
+    let mut ends: Vec<usize> = string
+        .char_indices()
+        .map(|(index, _character)| index)
+        .chain(Some(string.len()))
+        .collect();
+
+    if longest {
+        ends.reverse();
+    }
+
+    for end in ends {
+        if matches(pattern, &string[..end]) {
+            return &string[end..];
+        }
+    }
+
+    string
+}
+
+/// Remove the shortest, or longest, suffix of `string` matching `pattern`.
+#[inline]
+pub fn remove_suffix<'a>(string: &'a str, pattern: &str, longest: bool) -> &'a str {
+    let mut starts: Vec<usize> = string
+        .char_indices()
+        .map(|(index, _character)| index)
+        .chain(Some(string.len()))
+        .collect();
+
+    if !longest {
+        starts.reverse();
+    }
+
+    for start in starts {
+        if matches(pattern, &string[start..]) {
+            return &string[..start];
+        }
+    }
+
+    string
+}
+
+/// Replace the first, or every, longest match of `pattern` in `string` with `replacement`.
+#[inline]
+pub fn replace(string: &str, pattern: &str, replacement: &str, all: bool) -> String {
+    let boundaries: Vec<usize> = string
+        .char_indices()
+        .map(|(index, _character)| index)
+        .chain(Some(string.len()))
+        .collect();
+
+    let mut result = String::new();
+    let mut position = 0;
+    let mut replaced = false;
+
+    while position < boundaries.len() - 1 {
+        let start = boundaries[position];
+        let end = if replaced && !all {
+            None
+        } else {
+            boundaries[position + 1..]
+                .iter()
+                .rev()
+                .position(|end| matches(pattern, &string[start..*end]))
+                .map(|index| boundaries.len() - 1 - index)
+        };
+
+        match end {
+            Some(end) => {
+                result.push_str(replacement);
+                position = end;
+                replaced = true;
+            }
+            None => {
+                result.push_str(&string[start..boundaries[position + 1]]);
+                position += 1;
+            }
+        }
+    }
+
+    result
+}
@@ -60,6 +60,30 @@ impl Session {
         Ok(())
     }
 
+    /// Returns the current terminal modes.
+    #[inline]
+    pub fn termios(&self) -> io::Result<Termios> {
+        Termios::from_fd(self.as_raw_fd())
+    }
+
+    /// Restore terminal modes, i.e. those of a job being resumed.
+    #[inline]
+    pub fn set_termios(&self, termios: &Termios) -> io::Result<()> {
+        termios::tcsetattr(self.as_raw_fd(), termios::TCSADRAIN, termios)?;
+
+        Ok(())
+    }
+
+    /// Make `pgid` the foreground process group of the terminal.
+    #[inline]
+    pub fn set_foreground(&self, pgid: libc::pid_t) -> io::Result<()> {
+        if unsafe { libc::tcsetpgrp(self.as_raw_fd(), pgid) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
     #[inline]
     pub fn set_nonblocking(&self) -> io::Result<()> {
         unsafe {
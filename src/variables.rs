@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::{env, io};
+
+/// A shell variable.
+#[derive(Clone, Debug, Default)]
+pub struct Variable {
+    /// `None` when the variable is only declared, i.e. `export FOO` before `FOO` is set.
+    pub value: Option<String>,
+    /// Is the variable passed to programs?
+    pub exported: bool,
+    pub readonly: bool,
+}
+
+/// Shell variables, including those of the environment.
+#[derive(Debug, Default)]
+pub struct Variables {
+    map: BTreeMap<String, Variable>,
+}
+
+impl Variables {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            map: BTreeMap::new(),
+        }
+    }
+
+    /// Construct the variables from the environment, each exported.
+    #[inline]
+    pub fn from_env() -> Self {
+        let map = env::vars()
+            .map(|(name, value)| {
+                let variable = Variable {
+                    value: Some(value),
+                    exported: true,
+                    readonly: false,
+                };
+
+                (name, variable)
+            })
+            .collect();
+
+        Self { map }
+    }
+
+    /// Returns the value of a variable.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.map.get(name)?.value.clone()
+    }
+
+    /// Set, or remove a variable, returning it as it was.
+    ///
+    /// Fails if the variable is read-only.
+    #[inline]
+    pub fn replace<N>(
+        &mut self,
+        name: N,
+        variable: Option<Variable>,
+    ) -> io::Result<Option<Variable>>
+    where
+        N: Into<String>,
+    {
+        let name = name.into();
+
+        self.check_writable(&name)?;
+
+        let previous = match variable {
+            Some(variable) => self.map.insert(name, variable),
+            None => self.map.remove(&name),
+        };
+
+        Ok(previous)
+    }
+
+    /// Set the value of a variable, keeping whether it is exported.
+    ///
+    /// Fails if the variable is read-only.
+    #[inline]
+    pub fn set<N, V>(&mut self, name: N, value: V) -> io::Result<()>
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        let name = name.into();
+
+        self.check_writable(&name)?;
+        self.map.entry(name).or_default().value = Some(value.into());
+
+        Ok(())
+    }
+
+    /// Remove a variable.
+    ///
+    /// Fails if the variable is read-only.
+    #[inline]
+    pub fn unset(&mut self, name: &str) -> io::Result<()> {
+        self.replace(name, None).map(|_previous| ())
+    }
+
+    /// Export, or stop exporting a variable, declaring it if it doesn't exist.
+    #[inline]
+    pub fn export(&mut self, name: &str, exported: bool) {
+        self.map.entry(name.into()).or_default().exported = exported;
+    }
+
+    /// Make a variable read-only, declaring it if it doesn't exist.
+    #[inline]
+    pub fn make_readonly(&mut self, name: &str) {
+        self.map.entry(name.into()).or_default().readonly = true;
+    }
+
+    /// Iterate each variable, by name.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Variable)> {
+        self.map
+            .iter()
+            .map(|(name, variable)| (name.as_str(), variable))
+    }
+
+    /// Iterate the name and value of each exported variable which is set, the environment of
+    /// programs.
+    #[inline]
+    pub fn exported(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter()
+            .filter(|(_name, variable)| variable.exported)
+            .filter_map(|(name, variable)| Some((name, variable.value.as_deref()?)))
+    }
+
+    #[inline]
+    fn check_writable(&self, name: &str) -> io::Result<()> {
+        match self.map.get(name) {
+            Some(variable) if variable.readonly => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "read-only variable",
+            )),
+            _ => Ok(()),
+        }
+    }
+}